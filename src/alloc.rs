@@ -1,14 +1,136 @@
 use std::{
     process,
     io::{self, BufRead},
-    collections::{HashMap, hash_map},
+    collections::{BTreeMap, HashMap, VecDeque, hash_map, vec_deque},
     thread,
     sync::mpsc,
     time,
     ops, fmt,
 };
 
-use crate::components::memory::{self, MAX_LINE_COUNT, BYTES_PER_LINE};
+use crate::components::memory;
+use crate::breakpoint::{Breakpoint, Condition};
+use crate::history::History;
+use crate::fragmentation::Fragmentation;
+use crate::simulate::{Trace, TraceOp};
+use crate::lifetime::Lifetimes;
+use crate::sizes::Sizes;
+
+/// Address space covered by the index and shown on the memory map.
+pub const HEAP_SIZE: u64 = 256 * 1024;
+/// Chunks are indexed by the buckets of this many bytes they span, to find collisions without
+/// looking at every chunk.
+pub const BUCKET_SIZE: u64 = 1024;
+pub const BUCKET_COUNT: usize = (HEAP_SIZE / BUCKET_SIZE) as usize;
+/// Heap space that is neither live nor in the free list is reported only when larger than this,
+/// so the headers allocators keep between chunks are not reported.
+const UNACCOUNTED_TOLERANCE: u64 = 16;
+const DEFAULT_QUARANTINE_LIMIT: QuarantineLimit = QuarantineLimit::Chunks(1024);
+/// Chunks kept per address once gone, the oldest are forgotten first.
+const MAX_PAST_CHUNKS: usize = 32;
+/// Addresses whose past chunks are kept, the least recently used are forgotten first.
+const MAX_PAST_ADDRESSES: usize = 4096;
+
+/// Splits the type of an event from the thread (`@`) and the timestamp (`#`) that can follow it,
+/// in either order, as in `m@3#1f40`. Returns `None` when one of them is not a hex value.
+fn split_event_type(typ: &str) -> Option<(&str, Option<u64>, Option<u64>)> {
+    let end = typ.find(['@', '#']).unwrap_or(typ.len());
+    let (typ, mut rest) = typ.split_at(end);
+    let mut thread = None;
+    let mut target = None;
+    while let Some(marker) = rest.chars().next() {
+        let value_end = rest[1..].find(['@', '#']).map_or(rest.len(), |idx| idx + 1);
+        let value = u64::from_str_radix(&rest[1..value_end], 16).ok()?;
+        match marker {
+            '@' => thread = Some(value),
+            _ => target = Some(value),
+        }
+        rest = &rest[value_end..];
+    }
+    Some((typ, thread, target))
+}
+
+/// Parses a hex value typed by the user, with or without the `0x` prefix.
+pub fn parse_hex(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let value = value.strip_prefix("0x").unwrap_or(value);
+    u64::from_str_radix(value, 16).ok()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum AccessKind {
+    Read,
+    Write,
+}
+
+impl fmt::Display for AccessKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccessKind::Read => write!(f, "read"),
+            AccessKind::Write => write!(f, "write"),
+        }
+    }
+}
+
+/// What the integrity checker of the tracked process found when it reported a corruption.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CorruptionKind {
+    Unknown,
+    Canary,
+    Header,
+    FreeList,
+}
+
+impl CorruptionKind {
+    pub fn from_str(kind: &str) -> Self {
+        match kind {
+            "canary" => CorruptionKind::Canary,
+            "header" => CorruptionKind::Header,
+            "freelist" => CorruptionKind::FreeList,
+            _ => CorruptionKind::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for CorruptionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CorruptionKind::Unknown => write!(f, "unknown"),
+            CorruptionKind::Canary => write!(f, "canary smashed"),
+            CorruptionKind::Header => write!(f, "bad header"),
+            CorruptionKind::FreeList => write!(f, "bad free-list link"),
+        }
+    }
+}
+
+/// When an event happened. The host time is when its line was read, the target time is the
+/// optional timestamp the tracked process put on it, such as a cycle counter or RTOS ticks.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Timestamp {
+    /// Time since the tracking started.
+    pub host: time::Duration,
+    pub target: Option<u64>,
+}
+
+impl Timestamp {
+    /// The target timestamp follows `#` after the event type, as in `m#1f40:`.
+    pub fn from_line(line: &str, host: time::Duration) -> Self {
+        let target = line.split_once(':')
+            .and_then(|(typ, _)| split_event_type(typ))
+            .and_then(|(_, _, target)| target);
+        Self { host, target }
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.3} s", self.host.as_secs_f32())?;
+        if let Some(target) = self.target {
+            write!(f, " ({:#01x})", target)?;
+        }
+        Ok(())
+    }
+}
 
 #[derive(PartialEq)]
 pub enum Event {
@@ -16,33 +138,71 @@ pub enum Event {
         ptr: u64,
         size: u64,
         identifier: String,
+        thread: Option<u64>,
     },
     Free {
         ptr: u64,
         identifier: String,
+        thread: Option<u64>,
     },
     Corrupted {
-        ptr: u64
-    }
+        ptr: u64,
+        len: Option<u64>,
+        kind: CorruptionKind,
+        message: String,
+    },
+    Access {
+        ptr: u64,
+        len: u64,
+        kind: AccessKind,
+        identifier: String,
+    },
+    /// One block of a free list dump. Consecutive blocks form one dump.
+    FreeBlock {
+        ptr: u64,
+        size: u64,
+    },
 }
 
 impl Event {
     pub fn try_from_line(line: &str) -> Option<Self> {
         let (typ, data) = line.split_once(':')?;
+        // The type can be followed by the thread or task doing the allocation, as in `m@3:`.
+        let (typ, thread, _) = split_event_type(typ)?;
         let mut parts = data.split(',');
         let ptr = u64::from_str_radix(parts.next()?, 16).ok()?;
         match typ {
             "m" => {
                 let size = u64::from_str_radix(parts.next()?, 16).ok()?;
                 let identifier = parts.collect::<Vec<&str>>().join(",");
-                Some(Self::Alloc { ptr, size, identifier })
+                Some(Self::Alloc { ptr, size, identifier, thread })
             }
             "f" => {
                 let identifier = parts.collect::<Vec<&str>>().join(",");
-                Some(Self::Free { ptr, identifier })
+                Some(Self::Free { ptr, identifier, thread })
             }
             "c" => {
-                Some(Self::Corrupted { ptr })
+                // The length can be left empty or out. When it is left out, the kind directly
+                // follows the pointer; no kind is a hex value so they can't be mistaken.
+                let field = parts.next().unwrap_or_default();
+                let len = u64::from_str_radix(field, 16).ok();
+                let kind = match len.is_some() || field.is_empty() {
+                    true => parts.next().unwrap_or_default(),
+                    false => field,
+                };
+                let kind = CorruptionKind::from_str(kind);
+                let message = parts.collect::<Vec<&str>>().join(",");
+                Some(Self::Corrupted { ptr, len, kind, message })
+            }
+            "r" | "w" => {
+                let len = u64::from_str_radix(parts.next()?, 16).ok()?;
+                let kind = if typ == "r" { AccessKind::Read } else { AccessKind::Write };
+                let identifier = parts.collect::<Vec<&str>>().join(",");
+                Some(Self::Access { ptr, len, kind, identifier })
+            }
+            "l" => {
+                let size = u64::from_str_radix(parts.next()?, 16).ok()?;
+                Some(Self::FreeBlock { ptr, size })
             }
             _ => None
         }
@@ -53,17 +213,27 @@ impl Event {
 pub enum ChunkState {
     Ok,
     AlreadyUsed,
-    AlreadyFreed,
+    DoubleFree,
+    InvalidFree,
     Corrupted,
 }
 
 impl ChunkState {
+    pub const ALL: [ChunkState; 5] = [
+        ChunkState::Ok,
+        ChunkState::AlreadyUsed,
+        ChunkState::DoubleFree,
+        ChunkState::InvalidFree,
+        ChunkState::Corrupted,
+    ];
+
     pub fn to_color(&self) -> egui::Color32 {
         match self {
             ChunkState::Ok => memory::COLOR_USED,
             ChunkState::AlreadyUsed => memory::COLOR_ALREADY_USED,
             ChunkState::Corrupted => memory::COLOR_CORRUPTED,
-            ChunkState::AlreadyFreed => memory::COLOR_CORRUPTED,
+            ChunkState::DoubleFree => memory::COLOR_CORRUPTED,
+            ChunkState::InvalidFree => memory::COLOR_INVALID_FREE,
         }
     }
 }
@@ -74,30 +244,171 @@ impl fmt::Display for ChunkState {
             ChunkState::Ok => write!(f, "ok"),
             ChunkState::AlreadyUsed => write!(f, "already used"),
             ChunkState::Corrupted => write!(f, "corrupted"),
-            ChunkState::AlreadyFreed => write!(f, "already freed"),
+            ChunkState::DoubleFree => write!(f, "double free"),
+            ChunkState::InvalidFree => write!(f, "invalid free"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnomalyKind {
+    AlreadyUsed,
+    DoubleFree,
+    InvalidFree,
+    InteriorFree,
+    Corrupted(CorruptionKind),
+    UseAfterFree,
+    Overflow,
+    FreeListOverlap,
+    Unaccounted,
+    CrossThreadFree,
+}
+
+impl AnomalyKind {
+    /// Every kind of anomaly a policy can be set for.
+    pub const ALL: [AnomalyKind; 10] = [
+        AnomalyKind::AlreadyUsed,
+        AnomalyKind::DoubleFree,
+        AnomalyKind::InvalidFree,
+        AnomalyKind::InteriorFree,
+        AnomalyKind::Corrupted(CorruptionKind::Unknown),
+        AnomalyKind::UseAfterFree,
+        AnomalyKind::Overflow,
+        AnomalyKind::FreeListOverlap,
+        AnomalyKind::Unaccounted,
+        AnomalyKind::CrossThreadFree,
+    ];
+
+    /// Policies are set per kind of anomaly, whatever the kind of corruption is.
+    fn policy_key(self) -> Self {
+        match self {
+            AnomalyKind::Corrupted(_) => AnomalyKind::Corrupted(CorruptionKind::Unknown),
+            kind => kind,
+        }
+    }
+
+    fn default_policy(self) -> AnomalyPolicy {
+        match self {
+            AnomalyKind::Corrupted(_) => AnomalyPolicy::Pause,
+            // Freeing from another thread is often legit, it is only worth a look.
+            AnomalyKind::CrossThreadFree => AnomalyPolicy::Log,
+            _ => AnomalyPolicy::Continue,
+        }
+    }
+
+    pub fn to_color(self) -> egui::Color32 {
+        match self {
+            AnomalyKind::AlreadyUsed => memory::COLOR_ALREADY_USED,
+            AnomalyKind::DoubleFree => memory::COLOR_CORRUPTED,
+            AnomalyKind::InvalidFree => memory::COLOR_INVALID_FREE,
+            AnomalyKind::InteriorFree => memory::COLOR_INVALID_FREE,
+            AnomalyKind::Corrupted(_) => memory::COLOR_CORRUPTED,
+            AnomalyKind::UseAfterFree => memory::COLOR_USE_AFTER_FREE,
+            AnomalyKind::Overflow => memory::COLOR_OVERFLOW,
+            AnomalyKind::FreeListOverlap => memory::COLOR_ALREADY_USED,
+            AnomalyKind::Unaccounted => memory::COLOR_INVALID_FREE,
+            AnomalyKind::CrossThreadFree => memory::COLOR_CROSS_THREAD,
+        }
+    }
+}
+
+impl fmt::Display for AnomalyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnomalyKind::AlreadyUsed => write!(f, "already used"),
+            AnomalyKind::DoubleFree => write!(f, "double free"),
+            AnomalyKind::InvalidFree => write!(f, "invalid free"),
+            AnomalyKind::InteriorFree => write!(f, "interior free"),
+            AnomalyKind::Corrupted(CorruptionKind::Unknown) => write!(f, "corrupted"),
+            AnomalyKind::Corrupted(kind) => write!(f, "corrupted ({})", kind),
+            AnomalyKind::UseAfterFree => write!(f, "use after free"),
+            AnomalyKind::Overflow => write!(f, "overflow"),
+            AnomalyKind::FreeListOverlap => write!(f, "free block overlaps"),
+            AnomalyKind::Unaccounted => write!(f, "unaccounted space"),
+            AnomalyKind::CrossThreadFree => write!(f, "cross-thread free"),
+        }
+    }
+}
+
+/// What happens when an anomaly is noticed.
+#[derive(Clone, Copy, PartialEq)]
+pub enum AnomalyPolicy {
+    /// Stop applying events so the memory map stays as it was when the anomaly happened.
+    Pause,
+    /// Like `Pause` but also stops the tracked process with `SIGSTOP` so it can be inspected with a
+    /// debugger. It is resumed with `SIGCONT` when the view is resumed.
+    Breakpoint,
+    /// Keep applying events. The anomaly is painted on the memory map.
+    Continue,
+    /// Keep applying events. The anomaly is only listed, the memory map is left untouched.
+    Log,
+}
+
+impl AnomalyPolicy {
+    pub const ALL: [AnomalyPolicy; 4] = [
+        AnomalyPolicy::Pause,
+        AnomalyPolicy::Breakpoint,
+        AnomalyPolicy::Continue,
+        AnomalyPolicy::Log,
+    ];
+}
+
+impl fmt::Display for AnomalyPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnomalyPolicy::Pause => write!(f, "pause"),
+            AnomalyPolicy::Breakpoint => write!(f, "breakpoint"),
+            AnomalyPolicy::Continue => write!(f, "continue"),
+            AnomalyPolicy::Log => write!(f, "log"),
         }
     }
 }
 
+/// Something wrong that was noticed while applying an event.
+#[derive(Clone)]
+pub struct Anomaly {
+    pub kind: AnomalyKind,
+    pub ptr: u64,
+    /// Number of bytes touched starting at `ptr`. Zero when only the address is known.
+    pub len: u64,
+    /// The chunk the anomaly refers to, when `ptr` is not the start of a chunk itself.
+    pub owner: Option<u64>,
+    pub identifier: String,
+    /// Index of the event that caused the anomaly.
+    pub event_idx: usize,
+}
+
+/// How much of the freed chunks is kept in the quarantine, which the ghost outlines on the memory
+/// map are drawn from.
+#[derive(Clone, Copy, PartialEq)]
+pub enum QuarantineLimit {
+    Chunks(usize),
+    Bytes(u64),
+}
+
+/// Index buckets a chunk spans. Only the buckets of the index are kept, so a chunk reaching past
+/// `HEAP_SIZE` is only indexed up to there.
 #[derive(Clone)]
-pub struct ChunkLines {
+pub struct ChunkBuckets {
     pub start: usize,
     pub count: usize,
 }
 
-impl ChunkLines {
+impl ChunkBuckets {
     pub fn new(ptr: u64, size: u64) -> Self {
-        let start = ptr / BYTES_PER_LINE;
-        let start_x = ptr % BYTES_PER_LINE;
-        let count = if size < BYTES_PER_LINE - start_x {
+        let start = ptr / BUCKET_SIZE;
+        let start_x = ptr % BUCKET_SIZE;
+        let count = if size < BUCKET_SIZE - start_x {
             1
         } else {
-            (size - (BYTES_PER_LINE - start_x)) / BYTES_PER_LINE + 2
+            (size - (BUCKET_SIZE - start_x)) / BUCKET_SIZE + 2
         };
+        let end = (start + count).min(BUCKET_COUNT as u64);
+        let start = start.min(end);
 
         Self {
             start: start as usize,
-            count: count as usize
+            count: (end - start) as usize
         }
     }
 
@@ -108,103 +419,436 @@ impl ChunkLines {
 
 #[derive(Clone)]
 pub struct Chunk {
-    pub lines: ChunkLines,
+    pub buckets: ChunkBuckets,
     pub ptr: u64,
     pub size: u64,
     pub identifier: String,
+    pub free_identifiers: Vec<String>,
     pub state: ChunkState,
+    /// Thread or task that allocated the chunk, when the events carry it.
+    pub thread: Option<u64>,
+    pub free_thread: Option<u64>,
+    pub allocated_at: Option<Timestamp>,
+    pub freed_at: Option<Timestamp>,
+    /// Index of the events that allocated and freed the chunk.
+    pub allocated_event: usize,
+    pub freed_event: Option<usize>,
+    /// Chunks this one overlapped when it was allocated or that overlapped it later, with the
+    /// index of the event.
+    pub collisions: Vec<(usize, u64)>,
+    /// Painted for an anomaly without any memory of the tracked process behind it.
+    is_placeholder: bool,
 }
 
 impl Chunk {
     pub fn new(ptr: u64, size: u64, identifier: String) -> Self {
         Self {
-            lines: ChunkLines::new(ptr, size),
+            buckets: ChunkBuckets::new(ptr, size),
             ptr,
             size,
             identifier,
+            free_identifiers: Vec::new(),
             state: ChunkState::Ok,
+            thread: None,
+            free_thread: None,
+            allocated_at: None,
+            freed_at: None,
+            allocated_event: 0,
+            freed_event: None,
+            collisions: Vec::new(),
+            is_placeholder: false,
         }
     }
 
     pub fn placeholder(ptr: u64, state: ChunkState) -> Self {
         let size = 100;
         Self {
-            lines: ChunkLines::new(ptr, size),
+            buckets: ChunkBuckets::new(ptr, size),
             ptr,
             size,
             identifier: String::new(),
+            free_identifiers: Vec::new(),
             state,
+            thread: None,
+            free_thread: None,
+            allocated_at: None,
+            freed_at: None,
+            allocated_event: 0,
+            freed_event: None,
+            collisions: Vec::new(),
+            is_placeholder: true,
         }
     }
 
     fn is_solid(&self) -> bool {
-        self.state != ChunkState::AlreadyFreed && self.state != ChunkState::Corrupted
+        self.state == ChunkState::Ok || self.state == ChunkState::AlreadyUsed
+    }
+
+    pub fn is_bad_free(&self) -> bool {
+        self.state == ChunkState::DoubleFree || self.state == ChunkState::InvalidFree
+    }
+
+    /// Whether the chunk holds memory of the tracked process, which is what live bytes and gaps
+    /// are computed from. A chunk found corrupted still holds its memory.
+    pub(crate) fn is_allocated(&self) -> bool {
+        !self.is_placeholder && !self.is_bad_free()
+    }
+
+    fn bad_free_kind(&self) -> AnomalyKind {
+        match self.state {
+            ChunkState::DoubleFree => AnomalyKind::DoubleFree,
+            _ => AnomalyKind::InvalidFree,
+        }
     }
 
     pub fn is_colliding(&self, other: &Chunk) -> bool {
         (self.is_solid() && other.is_solid())
-            && ((self.ptr >= other.ptr && self.ptr < other.ptr + other.size)
-                || (other.ptr >= self.ptr && other.ptr < self.ptr + self.size))
+            && ((self.ptr >= other.ptr && self.ptr < other.end())
+                || (other.ptr >= self.ptr && other.ptr < self.end()))
     }
 
     pub fn set_state(&mut self, state: ChunkState) {
         self.state = state;
     }
+
+    /// Tells the chunk apart from the other chunks allocated at the same address.
+    pub fn key(&self) -> (u64, usize) {
+        (self.ptr, self.allocated_event)
+    }
+
+    /// Address past the last byte of the chunk. The size comes from the tracked process, so this
+    /// stops at the end of the address space.
+    pub fn end(&self) -> u64 {
+        self.ptr.saturating_add(self.size)
+    }
+
+    pub fn is_cross_thread_free(&self) -> bool {
+        matches!((self.thread, self.free_thread), (Some(a), Some(b)) if a != b)
+    }
 }
 
 pub struct Chunks {
-    rx: mpsc::Receiver<Event>,
+    rx: mpsc::Receiver<(Event, Timestamp)>,
+    /// The tracked process, when the events come from one.
+    process: Option<process::Child>,
+    is_process_stopped: bool,
+    pending: VecDeque<(Event, Timestamp)>,
     chunks: HashMap<u64, Chunk>,
-    line_lookup: [Vec<u64>; MAX_LINE_COUNT as usize],
-    pub do_advance: bool,
+    quarantine: VecDeque<Chunk>,
+    quarantine_bytes: u64,
+    quarantine_limit: QuarantineLimit,
+    anomalies: Vec<Anomaly>,
+    /// Chunks that were at an address before, oldest first, with when a chunk was last added.
+    past_chunks: HashMap<u64, (usize, VecDeque<Chunk>)>,
+    /// Addresses of `past_chunks` by when a chunk was last added, least recent first.
+    past_order: BTreeMap<usize, u64>,
+    past_count: usize,
+    policies: HashMap<AnomalyKind, AnomalyPolicy>,
+    breakpoints: Vec<Breakpoint>,
+    /// Index of the breakpoint that paused the view, until the view is resumed.
+    fired_breakpoint: Option<usize>,
+    bucket_lookup: [Vec<u64>; BUCKET_COUNT],
+    live_bytes: u64,
+    live_count: usize,
+    thread_live_bytes: HashMap<u64, u64>,
+    event_count: usize,
+    /// Host timestamp of the last event applied.
+    last_host: time::Duration,
+    history: History,
+    lifetimes: Lifetimes,
+    sizes: Sizes,
+    /// Addresses the allocator hands chunks out of. Gaps are only looked for in there.
+    heap: ops::Range<u64>,
+    fragmentation: Fragmentation,
+    /// Highest address ever covered by a live chunk.
+    peak_end: u64,
+    trace: Trace,
+    /// Blocks of the last free list dumped by the allocator.
+    free_list: Vec<ops::Range<u64>>,
+    is_reading_free_list: bool,
+    do_advance: bool,
 }
 
 impl Chunks {
-    pub fn new(mut command: process::Command, context: egui::Context) -> Self {
-        let (tx, rx) = mpsc::channel::<Event>();
+    /// Starts `command` and applies the events it prints. Fails when the process can't be started.
+    pub fn new(mut command: process::Command, context: egui::Context) -> io::Result<Self> {
+        let (tx, rx) = mpsc::channel::<(Event, Timestamp)>();
+        let start = time::Instant::now();
+        let mut process = command
+            .stdout(process::Stdio::piped())
+            .spawn()?;
+        let mut stdout = io::BufReader::new(process.stdout.take().unwrap());
         thread::spawn(move || {
-            let mut process = command
-                .stdout(process::Stdio::piped())
-                .spawn()
-                .unwrap();
-            let mut stdout = io::BufReader::new(process.stdout.take().unwrap());
             loop {
                 let mut input = String::new();
-                if let Ok(_) = stdout.read_line(&mut input) {
-                    if let Some(event) = Event::try_from_line(&input) {
-                        let _ = tx.send(event);
-                        context.request_repaint();
-                    }
+                // The process exited. Dropping `tx` lets the last events be applied as a whole.
+                if !matches!(stdout.read_line(&mut input), Ok(len) if len > 0) {
+                    context.request_repaint();
+                    break;
+                }
+                if let Some(event) = Event::try_from_line(&input) {
+                    let _ = tx.send((event, Timestamp::from_line(&input, start.elapsed())));
+                    context.request_repaint();
                 }
             }
         });
+        let mut chunks = Self::from_receiver(rx);
+        chunks.process = Some(process);
+        Ok(chunks)
+    }
+
+    /// Applies the events sent to `rx` instead of reading them from a process.
+    pub fn from_receiver(rx: mpsc::Receiver<(Event, Timestamp)>) -> Self {
         Self {
             rx,
+            process: None,
+            is_process_stopped: false,
+            pending: VecDeque::new(),
             chunks: HashMap::default(),
-            line_lookup: std::array::from_fn(|_| Vec::new()),
+            quarantine: VecDeque::new(),
+            quarantine_bytes: 0,
+            quarantine_limit: DEFAULT_QUARANTINE_LIMIT,
+            anomalies: Vec::new(),
+            past_chunks: HashMap::new(),
+            past_order: BTreeMap::new(),
+            past_count: 0,
+            policies: AnomalyKind::ALL
+                .iter()
+                .map(|&kind| (kind, kind.default_policy()))
+                .collect(),
+            breakpoints: Vec::new(),
+            fired_breakpoint: None,
+            bucket_lookup: std::array::from_fn(|_| Vec::new()),
+            live_bytes: 0,
+            live_count: 0,
+            thread_live_bytes: HashMap::new(),
+            event_count: 0,
+            last_host: time::Duration::ZERO,
+            history: History::new(),
+            lifetimes: Lifetimes::default(),
+            sizes: Sizes::default(),
+            heap: 0..HEAP_SIZE,
+            fragmentation: Fragmentation::default(),
+            peak_end: 0,
+            trace: Trace::default(),
+            free_list: Vec::new(),
+            is_reading_free_list: false,
             do_advance: true,
         }
     }
 
+    /// Applies the events received since the last update. When an anomaly or a breakpoint pauses
+    /// the view, the events following it are kept until the view is resumed.
     pub fn update(&mut self) -> bool {
-        let mut events = Vec::new();
         if !self.do_advance {
             return false;
         }
         while let Ok(event) = self.rx.recv_timeout(time::Duration::from_secs(0)) {
-            events.push(event);
-        }
-        let did_update = events.len() > 0;
-        for event in events {
-            match event {
-                Event::Free { ptr, .. } => self.free(ptr),
-                Event::Alloc { ptr, size, identifier } => self.alloc(ptr, size, identifier),
-                Event::Corrupted { ptr } => { self.corrupted(ptr); }
+            self.pending.push_back(event);
+        }
+        let did_update = !self.pending.is_empty();
+        // The event that fired a breakpoint is still pending when the view is resumed and must not
+        // fire it again.
+        let mut skip_breakpoints = self.fired_breakpoint.take().is_some();
+        while self.do_advance {
+            let Some((event, _)) = self.pending.front() else {
+                break;
+            };
+            if !skip_breakpoints {
+                if let Some(idx) = self.hit_breakpoint(event) {
+                    self.fired_breakpoint = Some(idx);
+                    self.do_advance = false;
+                    break;
+                }
+            }
+            skip_breakpoints = false;
+            if let Some((event, timestamp)) = self.pending.pop_front() {
+                self.apply(event, timestamp);
             }
         }
+        // A dump that came last, right before the process stopped or exited, is over as well.
+        if self.is_reading_free_list && self.pending.is_empty() {
+            self.check_free_list();
+        }
+        if did_update {
+            self.update_fragmentation();
+            self.history.push(self.event_count, self.last_host, self.live_bytes, self.live_count,
+                self.fragmentation.largest_size());
+        }
         did_update
     }
 
+    fn hit_breakpoint(&self, event: &Event) -> Option<usize> {
+        self.breakpoints
+            .iter()
+            .position(|breakpoint| {
+                breakpoint.is_enabled && breakpoint.condition.is_hit(event, self.live_bytes)
+            })
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    pub fn add_breakpoint(&mut self, condition: Condition) {
+        self.breakpoints.push(Breakpoint::new(condition));
+    }
+
+    pub fn remove_breakpoint(&mut self, idx: usize) {
+        if idx < self.breakpoints.len() {
+            self.breakpoints.remove(idx);
+            self.fired_breakpoint = None;
+        }
+    }
+
+    pub fn set_breakpoint_enabled(&mut self, idx: usize, is_enabled: bool) {
+        if let Some(breakpoint) = self.breakpoints.get_mut(idx) {
+            breakpoint.is_enabled = is_enabled;
+        }
+    }
+
+    pub fn fired_breakpoint(&self) -> Option<&Breakpoint> {
+        self.breakpoints.get(self.fired_breakpoint?)
+    }
+
+    /// Bytes held by the chunks on the memory map, the chunks painted for anomalies excluded.
+    pub fn live_bytes(&self) -> u64 {
+        self.live_bytes
+    }
+
+    pub fn live_count(&self) -> usize {
+        self.live_count
+    }
+
+    /// Events applied so far, used as a clock by the chunks' `allocated_event`.
+    pub fn event_count(&self) -> usize {
+        self.event_count
+    }
+
+    /// Live bytes of every thread that allocated something.
+    pub fn thread_live_bytes(&self) -> &HashMap<u64, u64> {
+        &self.thread_live_bytes
+    }
+
+    pub fn sizes(&self) -> &Sizes {
+        &self.sizes
+    }
+
+    pub fn lifetimes(&self) -> &Lifetimes {
+        &self.lifetimes
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    pub fn heap(&self) -> &ops::Range<u64> {
+        &self.heap
+    }
+
+    pub fn set_heap(&mut self, heap: ops::Range<u64>) {
+        self.heap = heap;
+        self.update_fragmentation();
+    }
+
+    pub fn fragmentation(&self) -> &Fragmentation {
+        &self.fragmentation
+    }
+
+    /// Ranges covered by live chunks, sorted by address.
+    pub fn live_ranges(&self) -> Vec<ops::Range<u64>> {
+        let mut ranges = self.chunks
+            .values()
+            .filter(|chunk| chunk.is_allocated())
+            .map(|chunk| chunk.ptr..chunk.end())
+            .collect::<Vec<ops::Range<u64>>>();
+        ranges.sort_unstable_by_key(|range| range.start);
+        ranges
+    }
+
+    /// Highest address ever used by a live chunk, relative to the start of the heap.
+    pub fn footprint(&self) -> u64 {
+        self.peak_end.saturating_sub(self.heap.start)
+    }
+
+    pub fn free_list(&self) -> &[ops::Range<u64>] {
+        &self.free_list
+    }
+
+    pub fn trace(&self) -> &Trace {
+        &self.trace
+    }
+
+    fn update_fragmentation(&mut self) {
+        self.fragmentation = Fragmentation::new(&self.heap, &self.live_ranges());
+    }
+
+    fn apply(&mut self, event: Event, timestamp: Timestamp) {
+        self.event_count += 1;
+        self.last_host = timestamp.host;
+        let is_free_block = matches!(event, Event::FreeBlock { .. });
+        if self.is_reading_free_list && !is_free_block {
+            self.check_free_list();
+        }
+        match event {
+            Event::Free { ptr, identifier, thread } => {
+                self.free(ptr, identifier, thread, timestamp);
+            }
+            Event::Alloc { ptr, size, identifier, thread } => {
+                self.alloc(ptr, size, identifier, thread, timestamp);
+            }
+            Event::Corrupted { ptr, len, kind, message } => {
+                self.corrupted(ptr, len, kind, message);
+            }
+            Event::Access { ptr, len, kind, identifier } => {
+                self.access(ptr, len, kind, identifier);
+            }
+            Event::FreeBlock { ptr, size } => self.free_block(ptr, size),
+        }
+    }
+
+    pub fn do_advance(&self) -> bool {
+        self.do_advance
+    }
+
+    pub fn set_do_advance(&mut self, do_advance: bool) {
+        self.do_advance = do_advance;
+        if do_advance {
+            self.set_process_stopped(false);
+        }
+    }
+
+    /// Stops or resumes the tracked process with `SIGSTOP` and `SIGCONT`. Other platforms than Unix
+    /// have no such signals, the process keeps running there.
+    fn set_process_stopped(&mut self, is_stopped: bool) {
+        if is_stopped == self.is_process_stopped {
+            return;
+        }
+        self.is_process_stopped = is_stopped;
+        #[cfg(unix)]
+        if let Some(process) = &self.process {
+            let signal = match is_stopped {
+                true => libc::SIGSTOP,
+                false => libc::SIGCONT,
+            };
+            // SAFETY: `kill` only sends a signal, it does not touch any memory.
+            unsafe {
+                libc::kill(process.id() as libc::pid_t, signal);
+            }
+        }
+    }
+
+    pub fn policy(&self, kind: AnomalyKind) -> AnomalyPolicy {
+        self.policies
+            .get(&kind.policy_key())
+            .copied()
+            .unwrap_or(kind.default_policy())
+    }
+
+    pub fn set_policy(&mut self, kind: AnomalyKind, policy: AnomalyPolicy) {
+        self.policies.insert(kind.policy_key(), policy);
+    }
+
     pub fn get(&self, ptr: u64) -> Option<&Chunk> {
         self.chunks.get(&ptr)
     }
@@ -213,55 +857,586 @@ impl Chunks {
         self.chunks.iter()
     }
 
-    fn is_chunk_colliding(&self, chunk: &Chunk) -> bool {
-        chunk.lines.range()
-            .into_iter()
-            .any(|idx| {
-                self.line_lookup[idx]
-                    .iter()
-                    .any(|ptr| {
-                        self.chunks.get(ptr)
-                            .map_or(false, |other| chunk.is_colliding(other))
+    /// Chunks spanning any of the index buckets in `buckets`, each once. `buckets` must be sorted.
+    pub fn in_buckets<'a>(&'a self, buckets: &'a [usize]) -> impl Iterator<Item = &'a Chunk> {
+        buckets.iter()
+            .filter_map(|&idx| Some((idx, self.bucket_lookup.get(idx)?)))
+            .flat_map(move |(idx, ptrs)| {
+                ptrs.iter()
+                    .filter_map(|ptr| self.chunks.get(ptr))
+                    // A chunk is listed in every bucket it spans, so it is only yielded from the
+                    // first of them that is in `buckets`.
+                    .filter(move |chunk| {
+                        let first = buckets.partition_point(|&b| b < chunk.buckets.start);
+                        buckets[first] == idx
+                    })
+            })
+    }
+
+    pub fn anomalies(&self) -> &[Anomaly] {
+        &self.anomalies
+    }
+
+    /// Chunks that were at `ptr` before the current one, oldest first.
+    pub fn past_chunks(&self, ptr: u64) -> impl DoubleEndedIterator<Item = &Chunk> {
+        self.past_chunks.get(&ptr).into_iter().flat_map(|(_, past)| past)
+    }
+
+    /// Returns the live chunk whose bytes contain `ptr`.
+    pub fn containing(&self, ptr: u64) -> Option<&Chunk> {
+        self.bucket_lookup
+            .get((ptr / BUCKET_SIZE) as usize)?
+            .iter()
+            .filter_map(|p| self.chunks.get(p))
+            .find(|chunk| chunk.is_solid() && ptr >= chunk.ptr && ptr < chunk.end())
+    }
+
+    /// Returns the chunk freed last whose bytes contained `ptr`, among the remembered ones. Like
+    /// `recently_freed`, this does not depend on the quarantine.
+    fn freed_containing(&self, ptr: u64) -> Option<&Chunk> {
+        self.past_chunks
+            .values()
+            .flat_map(|(_, past)| past)
+            .filter(|chunk| chunk.freed_event.is_some() && !chunk.is_bad_free())
+            .filter(|chunk| ptr >= chunk.ptr && ptr < chunk.end())
+            .max_by_key(|chunk| chunk.freed_event)
+    }
+
+    /// Records an anomaly and applies its policy. Returns `true` when the anomaly should be
+    /// painted on the memory map.
+    fn report(&mut self, kind: AnomalyKind, ptr: u64, len: u64, owner: Option<u64>,
+        identifier: String) -> bool {
+        let event_idx = self.event_count;
+        self.anomalies.push(Anomaly { kind, ptr, len, owner, identifier, event_idx });
+        match self.policy(kind) {
+            AnomalyPolicy::Pause => self.do_advance = false,
+            AnomalyPolicy::Breakpoint => {
+                self.do_advance = false;
+                self.set_process_stopped(true);
+            }
+            AnomalyPolicy::Continue => {}
+            AnomalyPolicy::Log => return false,
+        }
+        true
+    }
+
+    /// Returns the address of a live chunk sharing bytes with `chunk`.
+    fn colliding_chunk(&self, chunk: &Chunk) -> Option<u64> {
+        chunk.buckets.range()
+            .filter_map(|idx| self.bucket_lookup.get(idx))
+            .find_map(|ptrs| {
+                ptrs.iter()
+                    .copied()
+                    .find(|ptr| {
+                        self.chunks.get(ptr).is_some_and(|other| chunk.is_colliding(other))
                     })
             })
     }
 
     fn insert(&mut self, ptr: u64, chunk: Chunk) {
-        chunk.lines.range()
+        if let Some(replaced) = self.remove(ptr) {
+            self.remember_past(replaced);
+        }
+        if chunk.is_allocated() {
+            self.live_bytes += chunk.size;
+            self.live_count += 1;
+            self.history.observe(self.live_bytes, self.live_count);
+            if let Some(thread) = chunk.thread {
+                *self.thread_live_bytes.entry(thread).or_default() += chunk.size;
+            }
+            self.sizes.add_live(chunk.size);
+        }
+        chunk.buckets.range()
             .into_iter()
             .for_each(|idx| {
-                self.line_lookup[idx].push(ptr);
+                self.bucket_lookup[idx].push(ptr);
             });
         self.chunks.insert(ptr, chunk);
     }
 
-    fn alloc(&mut self, ptr: u64, size: u64, identifier: String) {
+    fn alloc(&mut self, ptr: u64, size: u64, identifier: String, thread: Option<u64>,
+        timestamp: Timestamp) {
+        self.trace.push(TraceOp::Alloc { ptr, size });
+        self.peak_end = self.peak_end.max(ptr.saturating_add(size));
+        self.sizes.observe_alloc(size, &identifier);
         let mut chunk = Chunk::new(ptr, size, identifier);
-        if self.is_chunk_colliding(&chunk) {
-            chunk.set_state(ChunkState::AlreadyUsed);
+        chunk.thread = thread;
+        chunk.allocated_at = Some(timestamp);
+        chunk.allocated_event = self.event_count;
+        if let Some(other) = self.colliding_chunk(&chunk) {
+            chunk.collisions.push((self.event_count, other));
+            if let Some(other) = self.chunks.get_mut(&other) {
+                other.collisions.push((self.event_count, ptr));
+            }
+            if self.report(AnomalyKind::AlreadyUsed, ptr, 0, None, chunk.identifier.clone()) {
+                chunk.set_state(ChunkState::AlreadyUsed);
+            }
         }
         self.insert(ptr, chunk);
     }
 
-    fn free(&mut self, ptr: u64) {
-        if let Some(chunk) = self.chunks.get(&ptr) {
-            chunk.lines.range()
-                .into_iter()
-                .for_each(|idx| {
-                    self.line_lookup[idx].retain(|&p| p != ptr);
-                });
-        } else {
-            self.insert(ptr, Chunk::placeholder(ptr, ChunkState::AlreadyFreed));
+    fn remove(&mut self, ptr: u64) -> Option<Chunk> {
+        let chunk = self.chunks.remove(&ptr)?;
+        chunk.buckets.range()
+            .into_iter()
+            .for_each(|idx| {
+                self.bucket_lookup[idx].retain(|&p| p != ptr);
+            });
+        if chunk.is_allocated() {
+            self.live_bytes -= chunk.size;
+            self.live_count -= 1;
+            if let Some(bytes) = chunk.thread.and_then(|t| self.thread_live_bytes.get_mut(&t)) {
+                *bytes -= chunk.size;
+            }
+            self.sizes.remove_live(chunk.size);
+        }
+        Some(chunk)
+    }
+
+    fn remember_past(&mut self, chunk: Chunk) {
+        self.past_count += 1;
+        let ptr = chunk.ptr;
+        let (last_used, past) = self.past_chunks.entry(ptr).or_default();
+        self.past_order.remove(last_used);
+        *last_used = self.past_count;
+        self.past_order.insert(self.past_count, ptr);
+        if past.len() == MAX_PAST_CHUNKS {
+            past.pop_front();
+        }
+        past.push_back(chunk);
+        if self.past_chunks.len() > MAX_PAST_ADDRESSES {
+            if let Some((_, forgotten)) = self.past_order.pop_first() {
+                self.past_chunks.remove(&forgotten);
+            }
+        }
+    }
+
+    fn remember_freed(&mut self, chunk: Chunk) {
+        self.quarantine_bytes += chunk.size;
+        self.quarantine.push_back(chunk);
+        self.trim_quarantine();
+    }
+
+    fn trim_quarantine(&mut self) {
+        loop {
+            let is_full = match self.quarantine_limit {
+                QuarantineLimit::Chunks(count) => self.quarantine.len() > count,
+                QuarantineLimit::Bytes(bytes) => self.quarantine_bytes > bytes,
+            };
+            if !is_full {
+                break;
+            }
+            match self.quarantine.pop_front() {
+                Some(chunk) => self.quarantine_bytes -= chunk.size,
+                None => break,
+            }
+        }
+    }
+
+    pub fn quarantine(&self) -> vec_deque::Iter<'_, Chunk> {
+        self.quarantine.iter()
+    }
+
+    pub fn quarantine_limit(&self) -> QuarantineLimit {
+        self.quarantine_limit
+    }
+
+    pub fn set_quarantine_limit(&mut self, limit: QuarantineLimit) {
+        self.quarantine_limit = limit;
+        self.trim_quarantine();
+    }
+
+    /// Returns the most recent chunk freed at `ptr`, if the address is still remembered. This does
+    /// not depend on the quarantine, so a small quarantine does not turn double frees into invalid
+    /// frees.
+    pub fn recently_freed(&self, ptr: u64) -> Option<&Chunk> {
+        self.past_chunks(ptr)
+            .rev()
+            .find(|chunk| chunk.freed_event.is_some() && !chunk.is_bad_free())
+    }
+
+    fn free(&mut self, ptr: u64, identifier: String, thread: Option<u64>,
+        timestamp: Timestamp) {
+        self.trace.push(TraceOp::Free { ptr });
+        // Freeing a chunk that is already painted as a bad free keeps all the free sites on it.
+        if let Some(chunk) = self.chunks.get_mut(&ptr).filter(|chunk| chunk.is_bad_free()) {
+            chunk.free_identifiers.push(identifier.clone());
+            let kind = chunk.bad_free_kind();
+            self.report(kind, ptr, 0, None, identifier);
+            return;
+        }
+        if let Some(mut chunk) = self.remove(ptr) {
+            chunk.free_thread = thread;
+            chunk.freed_at = Some(timestamp);
+            chunk.freed_event = Some(self.event_count);
+            self.lifetimes.observe(&chunk);
+            if chunk.is_cross_thread_free() {
+                self.report(AnomalyKind::CrossThreadFree, ptr, 0, None, identifier.clone());
+            }
+            chunk.free_identifiers.push(identifier);
+            self.remember_past(chunk.clone());
+            self.remember_freed(chunk);
+            return;
+        }
+        // The pointer is not live. It was either freed before (double free), points inside a live
+        // chunk (interior free) or was never returned by `malloc` at all (invalid free).
+        let mut chunk = match self.recently_freed(ptr) {
+            Some(freed) => {
+                let mut chunk = freed.clone();
+                chunk.set_state(ChunkState::DoubleFree);
+                chunk
+            }
+            None => {
+                if let Some(owner) = self.containing(ptr).map(|chunk| chunk.ptr) {
+                    self.report(AnomalyKind::InteriorFree, ptr, 0, Some(owner), identifier);
+                    return;
+                }
+                let mut chunk = Chunk::placeholder(ptr, ChunkState::InvalidFree);
+                chunk.allocated_event = self.event_count;
+                chunk
+            }
+        };
+        chunk.free_identifiers.push(identifier.clone());
+        chunk.freed_event = Some(self.event_count);
+        if self.report(chunk.bad_free_kind(), ptr, 0, None, identifier) {
+            self.insert(ptr, chunk);
         }
-        self.chunks.remove(&ptr);
     }
 
-    fn corrupted(&mut self, ptr: u64) {
-        self.do_advance = false;
+    /// Without a length only the address is known, so a placeholder chunk is painted unless a
+    /// chunk starts there. With a length the exact bytes are painted by the anomaly itself.
+    fn corrupted(&mut self, ptr: u64, len: Option<u64>, kind: CorruptionKind, message: String) {
+        // The length comes from the tracked process, only the bytes on the memory map are kept.
+        let len = len.map(|len| len.min(HEAP_SIZE.saturating_sub(ptr)));
+        let owner = self.containing(ptr).map(|chunk| chunk.ptr);
+        if !self.report(AnomalyKind::Corrupted(kind), ptr, len.unwrap_or(0), owner, message) {
+            return;
+        }
         if let Some(chunk) = self.chunks.get_mut(&ptr) {
             chunk.set_state(ChunkState::Corrupted);
-        } else {
-            self.insert(ptr, Chunk::placeholder(ptr, ChunkState::Corrupted));
+        } else if len.is_none() {
+            let mut chunk = Chunk::placeholder(ptr, ChunkState::Corrupted);
+            chunk.allocated_event = self.event_count;
+            self.insert(ptr, chunk);
+        }
+    }
+
+    /// Checks a read or a write against the live chunks. Touching a freed chunk is a use after
+    /// free, touching bytes past the end of a chunk or between chunks is an overflow.
+    fn access(&mut self, ptr: u64, len: u64, kind: AccessKind, identifier: String) {
+        let end = ptr.saturating_add(len.max(1));
+        let identifier = match identifier.is_empty() {
+            true => format!("{} of {} bytes", kind, len),
+            false => format!("{} of {} bytes by {}", kind, len, identifier),
+        };
+        match self.containing(ptr).map(|chunk| (chunk.ptr, chunk.end())) {
+            Some((owner, chunk_end)) if end > chunk_end => {
+                self.report(AnomalyKind::Overflow, chunk_end, end - chunk_end, Some(owner),
+                    identifier);
+            }
+            Some(_) => {}
+            None => {
+                let freed = self.freed_containing(ptr).map(|chunk| chunk.ptr);
+                let kind = match freed {
+                    Some(_) => AnomalyKind::UseAfterFree,
+                    None => AnomalyKind::Overflow,
+                };
+                self.report(kind, ptr, end - ptr, freed, identifier);
+            }
+        }
+    }
+
+    /// A free block overlapping a live chunk means the allocator lost track of that chunk.
+    fn free_block(&mut self, ptr: u64, size: u64) {
+        if !self.is_reading_free_list {
+            self.free_list.clear();
+            self.is_reading_free_list = true;
+        }
+        // The dump comes from the tracked process, a broken free list can have any size in it.
+        let size = ptr.saturating_add(size) - ptr;
+        self.free_list.push(ptr..ptr + size);
+        if let Some(owner) = self.colliding_chunk(&Chunk::new(ptr, size, String::new())) {
+            self.report(AnomalyKind::FreeListOverlap, ptr, size, Some(owner), String::new());
+        }
+    }
+
+    /// Once the whole free list was dumped, every byte of the heap should either be live or free.
+    fn check_free_list(&mut self) {
+        self.is_reading_free_list = false;
+        let mut free_list = self.free_list.clone();
+        free_list.sort_unstable_by_key(|block| block.start);
+        let gaps = Fragmentation::new(&self.heap, &self.live_ranges()).gaps;
+        for gap in gaps {
+            let unaccounted = Fragmentation::new(&gap, &free_list);
+            for range in unaccounted.gaps {
+                let len = range.end - range.start;
+                if len > UNACCOUNTED_TOLERANCE {
+                    self.report(AnomalyKind::Unaccounted, range.start, len, None, String::new());
+                }
+            }
         }
     }
 }
+
+impl Drop for Chunks {
+    /// A process stopped by a breakpoint would otherwise stay stopped once the viewer is closed.
+    fn drop(&mut self) {
+        self.set_process_stopped(false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulate::{Simulation, Strategy};
+
+    /// Applies `lines` as if the tracked process printed them.
+    fn chunks_from(lines: &[&str]) -> Chunks {
+        let (tx, rx) = mpsc::channel();
+        let start = time::Instant::now();
+        for line in lines {
+            let event = Event::try_from_line(line).unwrap();
+            tx.send((event, Timestamp::from_line(line, start.elapsed()))).unwrap();
+        }
+        let mut chunks = Chunks::from_receiver(rx);
+        chunks.update();
+        chunks
+    }
+
+    fn anomaly_kinds(chunks: &Chunks) -> Vec<AnomalyKind> {
+        chunks.anomalies().iter().map(|anomaly| anomaly.kind).collect()
+    }
+
+    #[test]
+    fn thread_and_timestamp_in_either_order() {
+        for line in ["m@3#1f40:100,20,site", "m#1f40@3:100,20,site"] {
+            let Some(Event::Alloc { ptr, size, identifier, thread }) = Event::try_from_line(line)
+            else {
+                panic!("{} is not an allocation", line);
+            };
+            assert_eq!((ptr, size, identifier.as_str(), thread), (0x100, 0x20, "site", Some(3)));
+            assert_eq!(Timestamp::from_line(line, time::Duration::ZERO).target, Some(0x1f40));
+        }
+        assert!(Event::try_from_line("m@3#zz:100,20,site").is_none());
+        assert!(Timestamp::from_line("m@3:100,20,site", time::Duration::ZERO).target.is_none());
+    }
+
+    #[test]
+    fn access_events_are_parsed() {
+        assert!(Event::try_from_line("r:100,8,reader") == Some(Event::Access {
+            ptr: 0x100, len: 8, kind: AccessKind::Read, identifier: String::from("reader") }));
+        assert!(Event::try_from_line("w@2:100,4") == Some(Event::Access {
+            ptr: 0x100, len: 4, kind: AccessKind::Write, identifier: String::new() }));
+        assert!(Event::try_from_line("r:100").is_none());
+        assert!(Event::try_from_line("w:100,zz,writer").is_none());
+    }
+
+    #[test]
+    fn access_inside_a_live_chunk_is_fine() {
+        let chunks = chunks_from(&["m:100,20,alloc_site", "r:100,20,reader", "w:11f,1,writer"]);
+        assert!(chunks.anomalies().is_empty());
+    }
+
+    #[test]
+    fn access_past_a_chunk_is_an_overflow() {
+        let chunks = chunks_from(&["m:100,20,alloc_site", "w:118,10,writer", "r:200,4,reader",
+            "w:fffffffffffffff8,10,writer"]);
+        assert!(anomaly_kinds(&chunks) == [AnomalyKind::Overflow; 3]);
+        let anomalies = chunks.anomalies().iter()
+            .map(|anomaly| (anomaly.ptr, anomaly.len, anomaly.owner))
+            .collect::<Vec<(u64, u64, Option<u64>)>>();
+        assert_eq!(anomalies[0], (0x120, 8, Some(0x100)));
+        assert_eq!(anomalies[1], (0x200, 4, None));
+        // The access stops at the end of the address space.
+        assert_eq!(anomalies[2], (0xfffffffffffffff8, 7, None));
+    }
+
+    #[test]
+    fn access_to_a_freed_chunk_does_not_depend_on_quarantine() {
+        let (_tx, rx) = mpsc::channel();
+        let mut chunks = Chunks::from_receiver(rx);
+        chunks.set_quarantine_limit(QuarantineLimit::Chunks(1));
+        for line in ["m:100,20,alloc_site", "m:200,20,alloc_site", "f:100,free_site",
+            "f:200,free_site", "m:100,8,reuse_site", "w:110,4,writer"] {
+            let event = Event::try_from_line(line).unwrap();
+            chunks.apply(event, Timestamp::from_line(line, time::Duration::ZERO));
+        }
+        assert!(anomaly_kinds(&chunks) == [AnomalyKind::UseAfterFree]);
+        assert_eq!(chunks.anomalies()[0].owner, Some(0x100));
+    }
+
+    #[test]
+    fn corruption_length_is_optional() {
+        let corruption = |line| match Event::try_from_line(line) {
+            Some(Event::Corrupted { len, kind, message, .. }) => (len, kind, message),
+            _ => panic!("{} is not a corruption", line),
+        };
+        assert!(corruption("c:100,10,header,bad size") ==
+            (Some(0x10), CorruptionKind::Header, "bad size".to_string()));
+        assert!(corruption("c:100,,canary,guard") ==
+            (None, CorruptionKind::Canary, "guard".to_string()));
+        assert!(corruption("c:100,canary") == (None, CorruptionKind::Canary, String::new()));
+        assert!(corruption("c:100") == (None, CorruptionKind::Unknown, String::new()));
+    }
+
+    #[test]
+    fn corruption_without_length_marks_a_chunk_or_a_placeholder() {
+        let (_tx, rx) = mpsc::channel();
+        let mut chunks = Chunks::from_receiver(rx);
+        for line in ["m:100,20,alloc_site", "c:100,,canary,guard", "c:200,canary",
+            "c:300,ffffffffff,canary"] {
+            let event = Event::try_from_line(line).unwrap();
+            chunks.apply(event, Timestamp::from_line(line, time::Duration::ZERO));
+        }
+        assert!(anomaly_kinds(&chunks) == [AnomalyKind::Corrupted(CorruptionKind::Canary); 3]);
+        assert!(chunks.get(0x100).unwrap().state == ChunkState::Corrupted);
+        assert!(chunks.get(0x200).unwrap().state == ChunkState::Corrupted);
+        assert_eq!(chunks.anomalies()[0].len, 0);
+        assert_eq!(chunks.anomalies()[2].len, HEAP_SIZE - 0x300);
+        assert!(chunks.get(0x300).is_none());
+    }
+
+    #[test]
+    fn corruption_placeholder_is_not_live() {
+        let chunks = chunks_from(&["m:100,20,alloc_site", "c:500", "c:100"]);
+        assert_eq!(chunks.live_bytes(), 0x20);
+        assert_eq!(chunks.live_count(), 1);
+        let chunk_range = 0x100..0x120;
+        assert!(chunks.live_ranges() == [chunk_range]);
+    }
+
+    #[test]
+    fn corruption_placeholder_has_no_size_class() {
+        let chunks = chunks_from(&["m:100,20,alloc_site", "c:500", "f:108,interior_free"]);
+        let peaks = chunks.sizes().classes().values().map(|class| class.peak);
+        assert!(peaks.eq([1]));
+    }
+
+    #[test]
+    fn free_block_past_the_index_is_checked_up_to_its_end() {
+        let chunks = chunks_from(&["m:3fe00,100,alloc_site", "l:3ff00,200", "l:3fe80,20",
+            "l:fffffffffffffff0,100", "m:100,10,alloc_site"]);
+        assert!(anomaly_kinds(&chunks).contains(&AnomalyKind::FreeListOverlap));
+        assert_eq!(chunks.anomalies()[0].owner, Some(0x3fe00));
+        assert_eq!(chunks.free_list().len(), 3);
+    }
+
+    #[test]
+    fn chunk_at_the_end_of_the_address_space_is_kept() {
+        let chunks = chunks_from(&["m:fffffffffffffff8,10,alloc_site",
+            "m:100,7ffffffffffffff8,big"]);
+        assert_eq!(chunks.live_count(), 2);
+        assert_eq!(chunks.footprint(), u64::MAX);
+        let simulation = Simulation::replay(Strategy::Tlsf, chunks.heap().clone(), chunks.trace());
+        assert_eq!(simulation.failed_count, 1);
+    }
+
+    /// Applies `lines` to a heap of `0..0x400`, where a free list is easy to dump whole.
+    fn small_heap_from(lines: &[&str]) -> Chunks {
+        let (tx, rx) = mpsc::channel();
+        for line in lines {
+            let event = Event::try_from_line(line).unwrap();
+            tx.send((event, Timestamp::from_line(line, time::Duration::ZERO))).unwrap();
+        }
+        let mut chunks = Chunks::from_receiver(rx);
+        chunks.set_heap(0..0x400);
+        chunks.update();
+        chunks
+    }
+
+    #[test]
+    fn free_list_is_checked_once_another_event_comes() {
+        let chunks = small_heap_from(&["m:100,20,alloc_site", "l:0,80", "l:120,2e0",
+            "m:380,10,alloc_site"]);
+        assert!(anomaly_kinds(&chunks) == [AnomalyKind::Unaccounted]);
+        assert_eq!((chunks.anomalies()[0].ptr, chunks.anomalies()[0].len), (0x80, 0x80));
+        assert_eq!(chunks.anomalies()[0].event_idx, 4);
+    }
+
+    #[test]
+    fn free_list_dumped_last_is_checked() {
+        let chunks = small_heap_from(&["m:100,20,alloc_site", "l:0,80", "l:120,2e0"]);
+        assert!(anomaly_kinds(&chunks) == [AnomalyKind::Unaccounted]);
+        assert_eq!((chunks.anomalies()[0].ptr, chunks.anomalies()[0].len), (0x80, 0x80));
+        let chunks = small_heap_from(&["m:100,20,alloc_site", "l:0,100", "l:120,2e0"]);
+        assert!(chunks.anomalies().is_empty());
+    }
+
+    #[test]
+    fn usage_sample_is_timed_by_last_event() {
+        let (tx, rx) = mpsc::channel();
+        for (line, seconds) in [("m:100,20,alloc_site", 5), ("f:100,free_site", 7)] {
+            let timestamp = Timestamp::from_line(line, time::Duration::from_secs(seconds));
+            tx.send((Event::try_from_line(line).unwrap(), timestamp)).unwrap();
+        }
+        let mut chunks = Chunks::from_receiver(rx);
+        chunks.update();
+        assert_eq!(chunks.history().samples()[0].seconds, 7.0);
+    }
+
+    #[test]
+    fn least_recently_used_addresses_are_forgotten() {
+        let mut lines = vec![String::from("m:0,10,first"), String::from("f:0,first")];
+        for idx in 1..=MAX_PAST_ADDRESSES as u64 {
+            lines.push(format!("m:{:x},10,site", idx * 0x10));
+            lines.push(format!("f:{:x},site", idx * 0x10));
+            if idx == 1 {
+                lines.push(String::from("m:0,10,second"));
+                lines.push(String::from("f:0,second"));
+            }
+        }
+        let chunks = chunks_from(&lines.iter().map(String::as_str).collect::<Vec<&str>>());
+        assert_eq!(chunks.past_chunks(0).count(), 2);
+        assert_eq!(chunks.past_chunks(0x10).count(), 0);
+        assert_eq!(chunks.past_chunks(0x20).count(), 1);
+        assert_eq!(chunks.past_chunks.len(), MAX_PAST_ADDRESSES);
+    }
+
+    #[test]
+    fn double_free_keeps_both_free_sites_and_the_allocation_site() {
+        let chunks = chunks_from(&["m:100,20,alloc_site", "f:100,first_free", "f:100,second_free"]);
+        assert!(anomaly_kinds(&chunks) == [AnomalyKind::DoubleFree]);
+        let chunk = chunks.get(0x100).unwrap();
+        assert!(chunk.state == ChunkState::DoubleFree);
+        assert_eq!(chunk.size, 0x20);
+        assert_eq!(chunk.identifier, "alloc_site");
+        assert_eq!(chunk.free_identifiers, ["first_free", "second_free"]);
+        assert_eq!(chunks.live_bytes(), 0);
+    }
+
+    #[test]
+    fn double_free_does_not_depend_on_quarantine() {
+        let (_tx, rx) = mpsc::channel();
+        let mut chunks = Chunks::from_receiver(rx);
+        chunks.set_quarantine_limit(QuarantineLimit::Chunks(0));
+        for line in ["m:100,20,alloc_site", "f:100,first_free", "f:100,second_free"] {
+            let event = Event::try_from_line(line).unwrap();
+            chunks.apply(event, Timestamp::from_line(line, time::Duration::ZERO));
+        }
+        assert_eq!(chunks.quarantine().count(), 0);
+        assert!(anomaly_kinds(&chunks) == [AnomalyKind::DoubleFree]);
+    }
+
+    #[test]
+    fn free_of_never_allocated_pointer_is_invalid() {
+        let chunks = chunks_from(&["m:100,20,alloc_site", "f:500,wild_free"]);
+        assert!(anomaly_kinds(&chunks) == [AnomalyKind::InvalidFree]);
+        let chunk = chunks.get(0x500).unwrap();
+        assert!(chunk.state == ChunkState::InvalidFree);
+        assert_eq!(chunk.free_identifiers, ["wild_free"]);
+        assert!(chunks.get(0x100).unwrap().state == ChunkState::Ok);
+    }
+
+    #[test]
+    fn free_inside_live_chunk_is_interior_and_paints_nothing() {
+        let chunks = chunks_from(&["m:100,20,alloc_site", "f:108,interior_free"]);
+        assert!(anomaly_kinds(&chunks) == [AnomalyKind::InteriorFree]);
+        assert_eq!(chunks.anomalies()[0].owner, Some(0x100));
+        // Only anomalies with a length are painted, and no placeholder chunk is added.
+        assert_eq!(chunks.anomalies()[0].len, 0);
+        assert!(chunks.get(0x108).is_none());
+        assert_eq!(chunks.iter().count(), 1);
+        assert!(chunks.get(0x100).unwrap().state == ChunkState::Ok);
+        assert_eq!(chunks.live_bytes(), 0x20);
+    }
+}
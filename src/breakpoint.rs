@@ -0,0 +1,117 @@
+use std::{ops, fmt};
+
+use crate::alloc::Event;
+
+#[derive(Clone, PartialEq)]
+pub enum Condition {
+    /// An allocation starting inside the range.
+    AllocInRange(ops::Range<u64>),
+    /// An event whose identifier matches the pattern. `*` matches any number of characters.
+    IdentifierMatches(String),
+    /// An allocation that would bring the live bytes above the limit.
+    LiveBytesAbove(u64),
+}
+
+impl Condition {
+    /// Checks the event before it is applied, `live_bytes` being the live bytes before it.
+    pub fn is_hit(&self, event: &Event, live_bytes: u64) -> bool {
+        match (self, event) {
+            (Condition::AllocInRange(range), Event::Alloc { ptr, .. }) => range.contains(ptr),
+            (Condition::IdentifierMatches(pattern), Event::Alloc { identifier, .. })
+                | (Condition::IdentifierMatches(pattern), Event::Free { identifier, .. })
+                | (Condition::IdentifierMatches(pattern), Event::Access { identifier, .. }) => {
+                matches_pattern(pattern, identifier)
+            }
+            (Condition::LiveBytesAbove(limit), Event::Alloc { size, .. }) => {
+                live_bytes.saturating_add(*size) > *limit
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Condition::AllocInRange(range) => {
+                write!(f, "alloc in {:#01x}..{:#01x}", range.start, range.end)
+            }
+            Condition::IdentifierMatches(pattern) => write!(f, "identifier matches {}", pattern),
+            Condition::LiveBytesAbove(limit) => write!(f, "live bytes above {}", limit),
+        }
+    }
+}
+
+pub struct Breakpoint {
+    pub condition: Condition,
+    pub is_enabled: bool,
+}
+
+impl Breakpoint {
+    pub fn new(condition: Condition) -> Self {
+        Self {
+            condition,
+            is_enabled: true,
+        }
+    }
+}
+
+/// Matches `text` against a pattern where `*` matches any number of characters.
+fn matches_pattern(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts = parts.collect::<Vec<&str>>();
+    let Some(last) = parts.pop() else {
+        // No `*` in the pattern, the whole text must match.
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn live_bytes_limit_holds_with_any_size() {
+        let condition = Condition::LiveBytesAbove(0x100);
+        let alloc = |size| Event::Alloc { ptr: 0, size, identifier: String::new(), thread: None };
+        assert!(!condition.is_hit(&alloc(0x80), 0x80));
+        assert!(condition.is_hit(&alloc(0x81), 0x80));
+        assert!(condition.is_hit(&alloc(u64::MAX), 0x80));
+    }
+
+    #[test]
+    fn pattern_with_trailing_star_matches_prefix() {
+        assert!(matches_pattern("lwip_*", "lwip_pbuf_alloc"));
+        assert!(matches_pattern("lwip_*", "lwip_"));
+        assert!(!matches_pattern("lwip_*", "xlwip_pbuf_alloc"));
+        assert!(!matches_pattern("lwip_*", "lwip"));
+    }
+
+    #[test]
+    fn pattern_with_several_stars_matches_in_order() {
+        assert!(matches_pattern("a*b*c", "abc"));
+        assert!(matches_pattern("a*b*c", "a_b_c"));
+        assert!(matches_pattern("a*b*c", "abbcc"));
+        assert!(!matches_pattern("a*b*c", "acb"));
+        assert!(!matches_pattern("a*b*c", "abcd"));
+        assert!(!matches_pattern("a*b*c", "ac"));
+    }
+
+    #[test]
+    fn pattern_without_star_matches_whole_text() {
+        assert!(matches_pattern("malloc", "malloc"));
+        assert!(!matches_pattern("malloc", "malloc_r"));
+        assert!(matches_pattern("*", ""));
+    }
+}
@@ -0,0 +1,45 @@
+/// Vertical bars with a label each, shown when a bar is hovered.
+pub struct BarChart<'a> {
+    bars: &'a [(String, usize)],
+    color: egui::Color32,
+}
+
+impl<'a> BarChart<'a> {
+    const HEIGHT: f32 = 80.0;
+    const GAP: f32 = 2.0;
+
+    pub fn new(bars: &'a [(String, usize)], color: egui::Color32) -> Self {
+        Self { bars, color }
+    }
+}
+
+impl egui::Widget for BarChart<'_> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let size = egui::Vec2::new(ui.available_width(), Self::HEIGHT);
+        let (mut response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+        let rect = response.rect;
+        painter.rect_filled(rect, 0.0, egui::Color32::from_gray(27));
+        let max = self.bars.iter().map(|(_, count)| *count).max().unwrap_or_default().max(1);
+        let width = rect.width() / self.bars.len().max(1) as f32;
+        let mut hovered = None;
+        for (idx, (label, count)) in self.bars.iter().enumerate() {
+            let height = *count as f32 / max as f32 * rect.height();
+            let bar = egui::Rect::from_min_max(
+                egui::Pos2::new(rect.left() + idx as f32 * width, rect.bottom() - height),
+                egui::Pos2::new(rect.left() + (idx + 1) as f32 * width - Self::GAP, rect.bottom()));
+            let column = egui::Rect::from_x_y_ranges(bar.x_range(), rect.y_range());
+            let color = match response.hover_pos().is_some_and(|pos| column.contains(pos)) {
+                true => {
+                    hovered = Some(format!("{}: {}", label, count));
+                    self.color.gamma_multiply(1.5)
+                }
+                false => self.color,
+            };
+            painter.rect_filled(bar, 0.0, color);
+        }
+        if let Some(text) = hovered {
+            response = response.on_hover_text_at_pointer(text);
+        }
+        response
+    }
+}
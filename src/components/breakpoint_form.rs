@@ -0,0 +1,97 @@
+use crate::alloc::parse_hex;
+use crate::breakpoint::Condition;
+
+#[derive(Clone, Copy, PartialEq)]
+enum ConditionKind {
+    AllocInRange,
+    IdentifierMatches,
+    LiveBytesAbove,
+}
+
+impl ConditionKind {
+    const ALL: [ConditionKind; 3] = [
+        ConditionKind::AllocInRange,
+        ConditionKind::IdentifierMatches,
+        ConditionKind::LiveBytesAbove,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            ConditionKind::AllocInRange => "Alloc in range",
+            ConditionKind::IdentifierMatches => "Identifier matches",
+            ConditionKind::LiveBytesAbove => "Live bytes above",
+        }
+    }
+}
+
+/// Inputs used to create a new breakpoint.
+pub struct BreakpointForm {
+    kind: ConditionKind,
+    start: String,
+    end: String,
+    pattern: String,
+    live_bytes: u64,
+}
+
+impl BreakpointForm {
+    pub fn new() -> Self {
+        Self {
+            kind: ConditionKind::AllocInRange,
+            start: String::new(),
+            end: String::new(),
+            pattern: String::new(),
+            live_bytes: 48 * 1024,
+        }
+    }
+
+    fn condition(&self) -> Option<Condition> {
+        match self.kind {
+            ConditionKind::AllocInRange => {
+                let start = parse_hex(&self.start)?;
+                let end = parse_hex(&self.end)?;
+                // An empty range would never be hit.
+                (start < end).then_some(Condition::AllocInRange(start..end))
+            }
+            ConditionKind::IdentifierMatches if !self.pattern.is_empty() => {
+                Some(Condition::IdentifierMatches(self.pattern.clone()))
+            }
+            ConditionKind::IdentifierMatches => None,
+            ConditionKind::LiveBytesAbove => Some(Condition::LiveBytesAbove(self.live_bytes)),
+        }
+    }
+
+    /// Shows the inputs and returns the condition to add when the add button is clicked.
+    pub fn show(&mut self, ui: &mut egui::Ui) -> Option<Condition> {
+        egui::ComboBox::from_id_source("breakpoint_kind")
+            .selected_text(self.kind.label())
+            .show_ui(ui, |ui| {
+                for kind in ConditionKind::ALL {
+                    ui.selectable_value(&mut self.kind, kind, kind.label());
+                }
+            });
+        ui.horizontal(|ui| {
+            match self.kind {
+                ConditionKind::AllocInRange => {
+                    ui.add(egui::TextEdit::singleline(&mut self.start)
+                        .hint_text("0x2000")
+                        .desired_width(80.0));
+                    ui.label("..");
+                    ui.add(egui::TextEdit::singleline(&mut self.end)
+                        .hint_text("0x2400")
+                        .desired_width(80.0));
+                }
+                ConditionKind::IdentifierMatches => {
+                    ui.add(egui::TextEdit::singleline(&mut self.pattern)
+                        .hint_text("lwip_*")
+                        .desired_width(180.0));
+                }
+                ConditionKind::LiveBytesAbove => {
+                    ui.add(egui::DragValue::new(&mut self.live_bytes).suffix(" bytes"));
+                }
+            }
+            let condition = self.condition();
+            let response = ui.add_enabled(condition.is_some(), egui::Button::new("Add"));
+            condition.filter(|_| response.clicked())
+        }).inner
+    }
+}
@@ -1,3 +1,11 @@
+/// Identifier of a chunk as shown to the user, chunks allocated without one included.
+pub fn identifier_label(identifier: &str) -> &str {
+    match identifier.is_empty() {
+        true => "(no identifier)",
+        false => identifier,
+    }
+}
+
 pub struct Field {
     title: String,
     value: String,
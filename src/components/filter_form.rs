@@ -0,0 +1,154 @@
+use crate::alloc::{parse_hex, ChunkState};
+use crate::filter::{Filter, IdentifierFilter};
+
+/// Inputs used to filter the chunks of the memory map.
+pub struct FilterForm {
+    identifier: String,
+    is_regex: bool,
+    ptr_start: String,
+    ptr_end: String,
+    size_min: String,
+    size_max: String,
+    state: Option<ChunkState>,
+    error: Option<String>,
+}
+
+impl FilterForm {
+    pub fn new() -> Self {
+        Self {
+            identifier: String::new(),
+            is_regex: false,
+            ptr_start: String::new(),
+            ptr_end: String::new(),
+            size_min: String::new(),
+            size_max: String::new(),
+            state: None,
+            error: None,
+        }
+    }
+
+    /// Builds the filter from the inputs. Empty inputs are left out of the filter.
+    fn filter(&mut self) -> Filter {
+        self.error = None;
+        let identifier = match (self.identifier.is_empty(), self.is_regex) {
+            (true, _) => None,
+            (false, false) => Some(IdentifierFilter::Substring(self.identifier.clone())),
+            (false, true) => match regex::Regex::new(&self.identifier) {
+                Ok(regex) => Some(IdentifierFilter::Regex(regex)),
+                Err(error) => {
+                    self.error = Some(error.to_string());
+                    None
+                }
+            },
+        };
+        let ptr = match (parse_hex(&self.ptr_start), parse_hex(&self.ptr_end)) {
+            (None, None) => None,
+            (start, end) => Some(start.unwrap_or(0)..end.unwrap_or(u64::MAX)),
+        };
+        let size = match (self.size_min.trim().parse().ok(), self.size_max.trim().parse().ok()) {
+            (None, None) => None,
+            (min, max) => Some(min.unwrap_or(0)..=max.unwrap_or(u64::MAX)),
+        };
+        Filter {
+            identifier,
+            ptr,
+            size,
+            state: self.state,
+        }
+    }
+
+    /// Replaces the inputs with a search for `identifier` and returns the filter.
+    pub fn set_identifier(&mut self, identifier: &str) -> Filter {
+        *self = Self::new();
+        self.identifier = identifier.to_string();
+        self.filter()
+    }
+
+    /// Shows the inputs. Returns the filter when one of them changed, and whether the view
+    /// should jump to the first match.
+    pub fn show(&mut self, ui: &mut egui::Ui) -> (Option<Filter>, bool) {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            changed |= ui.add(egui::TextEdit::singleline(&mut self.identifier)
+                .hint_text("identifier")
+                .desired_width(180.0)).changed();
+            changed |= ui.checkbox(&mut self.is_regex, "regex").changed();
+        });
+        ui.horizontal(|ui| {
+            ui.label("Ptr");
+            changed |= ui.add(egui::TextEdit::singleline(&mut self.ptr_start)
+                .hint_text("0x0")
+                .desired_width(80.0)).changed();
+            ui.label("..");
+            changed |= ui.add(egui::TextEdit::singleline(&mut self.ptr_end)
+                .hint_text("0x40000")
+                .desired_width(80.0)).changed();
+        });
+        ui.horizontal(|ui| {
+            ui.label("Size");
+            changed |= ui.add(egui::TextEdit::singleline(&mut self.size_min)
+                .hint_text("min")
+                .desired_width(80.0)).changed();
+            ui.label("..=");
+            changed |= ui.add(egui::TextEdit::singleline(&mut self.size_max)
+                .hint_text("max")
+                .desired_width(80.0)).changed();
+        });
+        let mut jump = false;
+        ui.horizontal(|ui| {
+            let state = self.state.map_or(String::from("any state"), |state| state.to_string());
+            egui::ComboBox::from_id_source("filter_state")
+                .selected_text(state)
+                .show_ui(ui, |ui| {
+                    changed |= ui.selectable_value(&mut self.state, None, "any state").changed();
+                    for state in ChunkState::ALL {
+                        changed |= ui.selectable_value(&mut self.state, Some(state),
+                            state.to_string()).changed();
+                    }
+                });
+            jump = ui.button("Jump to first").clicked();
+            if ui.button("Clear").clicked() {
+                *self = Self::new();
+                changed = true;
+            }
+        });
+        if let Some(error) = &self.error {
+            ui.label(egui::RichText::new(error).color(egui::Color32::RED));
+        }
+        match changed || jump {
+            true => (Some(self.filter()), jump),
+            false => (None, false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_regex_is_reported_and_left_out() {
+        let mut form = FilterForm::new();
+        form.identifier = String::from("lwip_(");
+        form.is_regex = true;
+        form.size_min = String::from("16");
+        let filter = form.filter();
+        assert!(form.error.is_some());
+        assert!(filter.identifier.is_none());
+        assert!(filter.size == Some(16..=u64::MAX));
+        form.identifier = String::from("lwip_(pbuf)");
+        assert!(form.filter().identifier.is_some());
+        assert!(form.error.is_none());
+    }
+
+    #[test]
+    fn open_ranges_are_bounded_by_the_address_space() {
+        let mut form = FilterForm::new();
+        form.ptr_end = String::from("0x200");
+        form.size_max = String::from("64");
+        let filter = form.filter();
+        assert!(filter.ptr == Some(0..0x200));
+        assert!(filter.size == Some(0..=64));
+        assert!(FilterForm::new().filter().is_empty());
+    }
+}
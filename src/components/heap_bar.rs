@@ -0,0 +1,138 @@
+use std::ops;
+
+use crate::alloc;
+use crate::components::memory;
+
+const OVERVIEW_HEIGHT_PX: f32 = 16.0;
+const DETAIL_HEIGHT_PX: f32 = 64.0;
+const SPACING_PX: f32 = 8.0;
+/// The detail bar doesn't zoom in further than this many bytes across.
+const MIN_VISIBLE_BYTES: f64 = 64.0;
+const COLOR_BACKGROUND: egui::Color32 = egui::Color32::from_gray(27);
+const COLOR_WINDOW: egui::Color32 = egui::Color32::WHITE;
+const COLOR_SELECTED: egui::Color32 = egui::Color32::LIGHT_GRAY;
+
+/// The heap as a single horizontal bar. An overview of the whole heap sits above a detail bar that
+/// zooms and pans over part of it.
+pub struct HeapBar {
+    /// Addresses shown by the detail bar.
+    window: ops::Range<f64>,
+}
+
+impl HeapBar {
+    pub fn new() -> Self {
+        Self {
+            window: 0.0..alloc::HEAP_SIZE as f64,
+        }
+    }
+
+    /// Zooms the window by `zoom` around `ptr`, keeping it within the heap.
+    fn zoom(&mut self, ptr: f64, zoom: f64) {
+        let heap = alloc::HEAP_SIZE as f64;
+        let span = ((self.window.end - self.window.start) / zoom).clamp(MIN_VISIBLE_BYTES, heap);
+        let ratio = (ptr - self.window.start) / (self.window.end - self.window.start);
+        self.pan_to(ptr - ratio * span, span);
+    }
+
+    /// Moves the window to start at `start`, keeping it within the heap.
+    fn pan_to(&mut self, start: f64, span: f64) {
+        let start = start.clamp(0.0, alloc::HEAP_SIZE as f64 - span);
+        self.window = start..start + span;
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, memory: &mut memory::Memory) -> egui::Response {
+        let heap = 0.0..alloc::HEAP_SIZE as f64;
+        let width = ui.available_width();
+        let span = self.window.end - self.window.start;
+
+        let (overview, painter) = ui.allocate_painter(egui::Vec2::new(width, OVERVIEW_HEIGHT_PX),
+            egui::Sense::click_and_drag());
+        paint_chunks(&painter, overview.rect, &heap, memory);
+        let window = egui::Rect::from_x_y_ranges(
+            x_of(overview.rect, &heap, self.window.start)..=x_of(overview.rect, &heap,
+                self.window.end),
+            overview.rect.y_range());
+        painter.rect_stroke(window, 0.0, egui::Stroke::new(1.0, COLOR_WINDOW));
+        if overview.clicked() || overview.dragged() {
+            if let Some(pos) = overview.interact_pointer_pos() {
+                self.pan_to(ptr_of(overview.rect, &heap, pos.x) - span / 2.0, span);
+            }
+        }
+
+        ui.add_space(SPACING_PX);
+        let (mut detail, painter) = ui.allocate_painter(egui::Vec2::new(width, DETAIL_HEIGHT_PX),
+            egui::Sense::click_and_drag());
+        let start = self.window.start - detail.drag_delta().x as f64 / width as f64 * span;
+        self.pan_to(start, span);
+        let zoom = ui.input(|i| i.zoom_delta()) as f64;
+        if let (Some(pos), true) = (detail.hover_pos(), zoom != 1.0) {
+            self.zoom(ptr_of(detail.rect, &self.window, pos.x), zoom);
+        }
+        paint_chunks(&painter, detail.rect, &self.window, memory);
+        if let Some(chunk) = memory.selected_chunk() {
+            let rect = range_rect(detail.rect, &self.window, chunk.ptr..chunk.end());
+            if let Some(rect) = rect {
+                painter.rect_stroke(rect, 0.0, egui::Stroke::new(1.0, COLOR_SELECTED));
+            }
+        }
+        let hovered = detail.hover_pos()
+            .and_then(|pos| {
+                memory.chunks().containing(ptr_of(detail.rect, &self.window, pos.x) as u64)
+            })
+            .map(|chunk| (chunk.ptr, format!("{:#01x} ({} bytes)\n{}", chunk.ptr, chunk.size,
+                chunk.identifier)));
+        if detail.clicked() {
+            memory.select(hovered.as_ref().map(|(ptr, _)| *ptr));
+        }
+        if let Some((_, text)) = hovered {
+            detail = detail.on_hover_text_at_pointer(text);
+        }
+
+        let labels = ui.horizontal(|ui| {
+            ui.label(format!("{:#01x}", self.window.start as u64));
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.label(format!("{:#01x}", self.window.end as u64));
+            });
+        }).response;
+        overview | detail | labels
+    }
+}
+
+fn x_of(rect: egui::Rect, window: &ops::Range<f64>, ptr: f64) -> f32 {
+    rect.left() + ((ptr - window.start) / (window.end - window.start)) as f32 * rect.width()
+}
+
+fn ptr_of(rect: egui::Rect, window: &ops::Range<f64>, x: f32) -> f64 {
+    let ratio = ((x - rect.left()) / rect.width()) as f64;
+    window.start + ratio * (window.end - window.start)
+}
+
+/// Part of the bar covered by `range`. Ranges in view are at least a pixel wide.
+fn range_rect(rect: egui::Rect, window: &ops::Range<f64>, range: ops::Range<u64>)
+    -> Option<egui::Rect> {
+    if range.end as f64 <= window.start || range.start as f64 >= window.end {
+        return None;
+    }
+    let left = x_of(rect, window, range.start as f64).max(rect.left());
+    let right = x_of(rect, window, range.end as f64).min(rect.right()).max(left + 1.0);
+    Some(egui::Rect::from_x_y_ranges(left..=right, rect.y_range()))
+}
+
+fn paint_chunks(painter: &egui::Painter, rect: egui::Rect, window: &ops::Range<f64>,
+    memory: &memory::Memory) {
+    painter.rect_filled(rect, 0.0, COLOR_BACKGROUND);
+    for chunk in memory.chunks().iter().map(|(_, chunk)| chunk) {
+        let Some(color) = memory.painted_color(chunk) else {
+            continue;
+        };
+        if let Some(chunk_rect) = range_rect(rect, window, chunk.ptr..chunk.end()) {
+            painter.rect_filled(chunk_rect, 0.0, color);
+        }
+    }
+    for anomaly in memory.painted_anomalies() {
+        if let Some(anomaly_rect) = range_rect(rect, window,
+            anomaly.ptr..anomaly.ptr.saturating_add(anomaly.len)) {
+            painter.rect_filled(anomaly_rect, 0.0, anomaly.kind.to_color());
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use crate::alloc::ChunkState;
+use crate::components::identifier_label;
+use crate::components::memory::{self, ColorBy};
+
+const SWATCH_SIZE: egui::Vec2 = egui::Vec2::new(12.0, 12.0);
+const MAX_IDENTIFIER_COUNT: usize = 12;
+const AGE_STEPS: [usize; 5] = [0, 16, 256, 4096, memory::MAX_AGE_EVENTS];
+
+/// Explains the colours of the chunks for the current colour-by mode.
+pub struct Legend<'a> {
+    memory: &'a memory::Memory,
+}
+
+impl<'a> Legend<'a> {
+    pub fn new(memory: &'a memory::Memory) -> Self {
+        Self {
+            memory,
+        }
+    }
+
+    /// Colours and what they stand for. The second value is the number of entries left out.
+    fn entries(&self) -> (Vec<(egui::Color32, String)>, usize) {
+        let chunks = self.memory.chunks();
+        let mut hidden_count = 0;
+        let mut entries = match self.memory.color_by() {
+            ColorBy::State => Vec::new(),
+            ColorBy::Identifier => {
+                let mut bytes = HashMap::<&str, u64>::new();
+                for chunk in chunks.iter().map(|(_, chunk)| chunk).filter(|c| c.is_allocated()) {
+                    *bytes.entry(&chunk.identifier).or_default() += chunk.size;
+                }
+                let mut identifiers = bytes.into_iter().collect::<Vec<(&str, u64)>>();
+                identifiers.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+                hidden_count = identifiers.len().saturating_sub(MAX_IDENTIFIER_COUNT);
+                identifiers.into_iter()
+                    .take(MAX_IDENTIFIER_COUNT)
+                    .map(|(identifier, _)| {
+                        (memory::identifier_color(identifier),
+                            identifier_label(identifier).to_string())
+                    })
+                    .collect()
+            }
+            ColorBy::Age => AGE_STEPS
+                .iter()
+                .map(|&age| {
+                    let label = match age == memory::MAX_AGE_EVENTS {
+                        true => format!("{}+ events old", age),
+                        false => format!("{} events old", age),
+                    };
+                    (memory::age_color(age), label)
+                })
+                .collect(),
+            ColorBy::Size => (memory::MIN_SIZE_CLASS.ilog2()..=memory::MAX_SIZE_CLASS.ilog2())
+                .step_by(2)
+                .map(|exponent| {
+                    let size = 1 << exponent;
+                    (memory::size_color(size), format!("up to {} bytes", size))
+                })
+                .collect(),
+            ColorBy::Thread => {
+                let mut threads = chunks.thread_live_bytes().keys().copied().collect::<Vec<u64>>();
+                threads.sort_unstable();
+                threads.into_iter()
+                    .map(|thread| (memory::thread_color(thread), format!("thread {:x}", thread)))
+                    .collect()
+            }
+        };
+        // Chunks in a bad state keep their state colour whatever the mode.
+        entries.extend(ChunkState::ALL
+            .into_iter()
+            .filter(|&state| state != ChunkState::Ok || self.memory.color_by() == ColorBy::State)
+            .map(|state| (state.to_color(), state.to_string())));
+        (entries, hidden_count)
+    }
+}
+
+impl egui::Widget for Legend<'_> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let (entries, hidden_count) = self.entries();
+        ui.vertical(|ui| {
+            for (color, label) in entries {
+                ui.horizontal(|ui| {
+                    let (rect, _) = ui.allocate_exact_size(SWATCH_SIZE, egui::Sense::hover());
+                    ui.painter().rect_filled(rect, 2.0, color);
+                    ui.label(label);
+                });
+            }
+            if hidden_count > 0 {
+                ui.label(format!("and {} more identifiers", hidden_count));
+            }
+        }).response
+    }
+}
@@ -1,21 +1,258 @@
-use crate::alloc;
+use std::{collections::{hash_map, BTreeSet, HashSet}, fmt, hash::{Hash, Hasher}, ops};
 
-pub const BYTES_PER_LINE: u64 = 1024;
+use crate::{alloc, breakpoint, filter, hilbert, simulate, sizes};
+
+/// Row widths offered in the side panel. Every one of them is a power of two.
+pub const BYTES_PER_LINE_OPTIONS: [u64; 5] = [256, 512, 1024, 2048, 4096];
+const DEFAULT_BYTES_PER_LINE: u64 = 1024;
+pub const BYTE_WIDTH_PX_RANGE: ops::RangeInclusive<u64> = 1..=8;
+const DEFAULT_BYTE_WIDTH_PX: u64 = 3;
 const LINE_HEIGHT_PX: u64 = 32;
-const BYTE_WIDTH_PX: u64 = 3;
-pub const MAX_LINE_COUNT: u64 = 262144 / BYTES_PER_LINE;
+const HIGHLIGHT_MARGIN_PX: f32 = 4.0;
+/// Chunks this many events old or more get the same colour when coloured by age.
+pub const MAX_AGE_EVENTS: usize = 1 << 16;
+pub const MIN_SIZE_CLASS: u64 = 8;
+pub const MAX_SIZE_CLASS: u64 = 64 * 1024;
+/// When zoomed out so far that a byte is narrower than this, and more chunks than
+/// `MIN_DENSITY_CHUNK_COUNT` are in view, chunks are not drawn one by one. The live bytes of every
+/// block of `DENSITY_BLOCK_SIZE` bytes are drawn as a texel of a texture instead, along with the
+/// anomalies and the free list. Outlines of freed and simulated chunks are left out then.
+const MAX_DENSITY_BYTE_PX: f32 = 0.5;
+const MIN_DENSITY_CHUNK_COUNT: usize = 2000;
+/// A power of four, so a block fills a square with the Hilbert layout.
+const DENSITY_BLOCK_SIZE: u64 = 16;
+const CACHE_LINE_SIZE: u64 = 64;
+const PAGE_SIZE: u64 = 4096;
+/// Gridlines closer than this on the screen are not drawn.
+const MIN_GRIDLINE_SPACING_PX: f32 = 4.0;
+const RULER_WIDTH_PX: f32 = 56.0;
+const RULER_HEIGHT_PX: f32 = 16.0;
+const RULER_PADDING_PX: f32 = 3.0;
+const RULER_FONT_SIZE: f32 = 10.0;
+const MIN_ROW_LABEL_SPACING_PX: f32 = 14.0;
+const MIN_COLUMN_LABEL_SPACING_PX: f32 = 48.0;
 
 pub const COLOR_USED: egui::Color32 = egui::Color32::from_rgb(41, 128, 185);
 pub const COLOR_ALREADY_USED: egui::Color32 = egui::Color32::from_rgb(231, 76, 60);
 pub const COLOR_CORRUPTED: egui::Color32 = egui::Color32::from_rgb(142, 68, 173);
+pub const COLOR_INVALID_FREE: egui::Color32 = egui::Color32::from_rgb(230, 126, 34);
+pub const COLOR_USE_AFTER_FREE: egui::Color32 = egui::Color32::from_rgb(232, 67, 147);
+pub const COLOR_OVERFLOW: egui::Color32 = egui::Color32::from_rgb(241, 196, 15);
+pub const COLOR_CROSS_THREAD: egui::Color32 = egui::Color32::from_rgb(52, 231, 228);
+const COLOR_QUARANTINE: egui::Color32 = egui::Color32::from_gray(110);
+const COLOR_LARGEST_GAP: egui::Color32 = egui::Color32::from_rgb(46, 204, 113);
+const COLOR_FREE_BLOCK: egui::Color32 = egui::Color32::from_rgb(39, 174, 96);
+const COLOR_PREDICTION: egui::Color32 = egui::Color32::WHITE;
+const COLOR_SIMULATION: egui::Color32 = egui::Color32::from_rgb(26, 188, 156);
+const COLOR_HIGHLIGHTED: egui::Color32 = egui::Color32::YELLOW;
 const COLOR_SELECTED: egui::Color32 = egui::Color32::LIGHT_GRAY;
+const COLOR_PINNED: egui::Color32 = egui::Color32::from_rgb(253, 203, 110);
 const COLOR_HOVERD: egui::Color32 = egui::Color32::from_rgb(142, 68, 173);
+const COLOR_GRIDLINE: egui::Color32 = egui::Color32::from_gray(70);
+const COLOR_RULER: egui::Color32 = egui::Color32::GRAY;
+const COLOR_RULER_BACKGROUND: egui::Color32 = egui::Color32::from_black_alpha(200);
+
+/// What the colour of a healthy chunk tells. Chunks in a bad state always keep their state colour.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ColorBy {
+    State,
+    Identifier,
+    Age,
+    Size,
+    Thread,
+}
+
+impl ColorBy {
+    pub const ALL: [ColorBy; 5] = [
+        ColorBy::State,
+        ColorBy::Identifier,
+        ColorBy::Age,
+        ColorBy::Size,
+        ColorBy::Thread,
+    ];
+}
+
+impl fmt::Display for ColorBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColorBy::State => write!(f, "state"),
+            ColorBy::Identifier => write!(f, "identifier"),
+            ColorBy::Age => write!(f, "age"),
+            ColorBy::Size => write!(f, "size"),
+            ColorBy::Thread => write!(f, "thread"),
+        }
+    }
+}
+
+/// How addresses are laid out on the canvas.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Layout {
+    /// Rows of bytes, one under another.
+    Rows,
+    /// Bytes follow a Hilbert curve over a square, so close addresses stay close on the screen.
+    Hilbert,
+}
+
+impl Layout {
+    pub const ALL: [Layout; 2] = [Layout::Rows, Layout::Hilbert];
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Layout::Rows => write!(f, "rows"),
+            Layout::Hilbert => write!(f, "Hilbert curve"),
+        }
+    }
+}
+
+/// Boundaries drawn over the memory map to reason about alignment.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Gridlines {
+    None,
+    CacheLine,
+    Page,
+}
+
+impl Gridlines {
+    pub const ALL: [Gridlines; 3] = [Gridlines::None, Gridlines::CacheLine, Gridlines::Page];
+
+    fn spacing(self) -> Option<u64> {
+        match self {
+            Gridlines::None => None,
+            Gridlines::CacheLine => Some(CACHE_LINE_SIZE),
+            Gridlines::Page => Some(PAGE_SIZE),
+        }
+    }
+}
+
+impl fmt::Display for Gridlines {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Gridlines::None => write!(f, "none"),
+            Gridlines::CacheLine => write!(f, "cache line ({} B)", CACHE_LINE_SIZE),
+            Gridlines::Page => write!(f, "page ({} KiB)", PAGE_SIZE / 1024),
+        }
+    }
+}
+
+/// Smallest power of two of units whose labels are at least `min_px` apart, when a unit is
+/// `unit_px` long on the screen.
+fn label_step(unit_px: f32, min_px: f32) -> u64 {
+    let mut step = 1;
+    while (step as f32) * unit_px < min_px {
+        step *= 2;
+    }
+    step
+}
+
+/// Whether `ptr..ptr + size` spans one of `visible_buckets`, which must be sorted.
+fn is_range_visible(visible_buckets: &[usize], ptr: u64, size: u64) -> bool {
+    let range = alloc::ChunkBuckets::new(ptr, size).range();
+    let first = visible_buckets.partition_point(|&idx| idx < range.start);
+    visible_buckets.get(first).is_some_and(|&idx| idx < range.end)
+}
+
+/// Addresses covered by `visible_buckets`, which must be sorted, merged into runs.
+fn visible_ranges(visible_buckets: &[usize]) -> Vec<ops::Range<u64>> {
+    let mut ranges: Vec<ops::Range<u64>> = Vec::new();
+    for &idx in visible_buckets {
+        let start = idx as u64 * alloc::BUCKET_SIZE;
+        match ranges.last_mut() {
+            Some(range) if range.end == start => range.end += alloc::BUCKET_SIZE,
+            _ => ranges.push(start..start + alloc::BUCKET_SIZE),
+        }
+    }
+    ranges
+}
+
+/// Parts of `range` inside `visible_ranges`, which must be sorted.
+fn visible_parts(visible_ranges: &[ops::Range<u64>], range: ops::Range<u64>)
+    -> impl Iterator<Item = ops::Range<u64>> + '_ {
+    visible_ranges.iter()
+        .map(move |visible| range.start.max(visible.start)..range.end.min(visible.end))
+        .filter(|part| !part.is_empty())
+}
+
+/// Blocks of `DENSITY_BLOCK_SIZE` bytes covered by `ptr..ptr + size`, each cut to that range.
+fn density_blocks(ptr: u64, size: u64) -> impl Iterator<Item = ops::Range<u64>> {
+    let end = ptr.saturating_add(size).min(alloc::HEAP_SIZE);
+    let block_end = move |ptr: u64| ((ptr / DENSITY_BLOCK_SIZE + 1) * DENSITY_BLOCK_SIZE).min(end);
+    std::iter::successors(Some(ptr).filter(|&ptr| ptr < end), move |&ptr| {
+        Some(block_end(ptr)).filter(|&next| next < end)
+    })
+    .map(move |ptr| ptr..block_end(ptr))
+}
+
+/// Stable colour of an identifier, picked from its hash.
+pub fn identifier_color(identifier: &str) -> egui::Color32 {
+    let mut hasher = hash_map::DefaultHasher::new();
+    identifier.hash(&mut hasher);
+    let hue = (hasher.finish() % 360) as f32 / 360.0;
+    egui::ecolor::Hsva::new(hue, 0.5, 0.8, 1.0).into()
+}
+
+/// Colour of a chunk allocated `age` events ago, from yellow for new chunks to dark blue for
+/// chunks `MAX_AGE_EVENTS` old or more. Ages are on a log scale.
+pub fn age_color(age: usize) -> egui::Color32 {
+    let ratio = (((age + 1) as f32).log2() / (MAX_AGE_EVENTS as f32).log2()).min(1.0);
+    egui::ecolor::Hsva::new(0.15 + 0.5 * ratio, 0.7, 0.95 - 0.5 * ratio, 1.0).into()
+}
+
+/// Colour of the size class of a chunk, from green for `MIN_SIZE_CLASS` to purple for
+/// `MAX_SIZE_CLASS` and above.
+pub fn size_color(size: u64) -> egui::Color32 {
+    let class = sizes::class_of(size).clamp(MIN_SIZE_CLASS, MAX_SIZE_CLASS);
+    let ratio = (class.ilog2() - MIN_SIZE_CLASS.ilog2()) as f32
+        / (MAX_SIZE_CLASS.ilog2() - MIN_SIZE_CLASS.ilog2()) as f32;
+    egui::ecolor::Hsva::new(0.3 + 0.45 * ratio, 0.7, 0.85, 1.0).into()
+}
+
+/// Stable colour of a thread. Successive ids are spread around the hue wheel.
+pub fn thread_color(thread: u64) -> egui::Color32 {
+    let hue = (thread as f32 * 0.618_034).fract();
+    egui::ecolor::Hsva::new(hue, 0.6, 0.85, 1.0).into()
+}
+
+/// A chunk picked by the user, as its address and the event that allocated it. A chunk allocated
+/// later at the same address is not mistaken for it.
+pub type ChunkKey = (u64, usize);
 
 pub struct Memory {
     chunks: alloc::Chunks,
     translation: egui::Vec2,
     scale: f32,
+    /// Address shown in the side panel. The chunk there, while it lives, is also in `selection`.
     selected_ptr: Option<u64>,
+    selection: BTreeSet<ChunkKey>,
+    /// Where a shift-drag started on the canvas, to select every chunk in a box.
+    box_start: Option<egui::Pos2>,
+    hidden: HashSet<ChunkKey>,
+    /// Chunks outlined and listed in the side panel until unpinned.
+    pinned: BTreeSet<ChunkKey>,
+    /// Where a simulated allocator would put the next allocation.
+    prediction: Option<ops::Range<u64>>,
+    /// Layout of a simulated allocator, drawn over the real one to compare them.
+    simulation: Option<simulate::Simulation>,
+    color_by: ColorBy,
+    /// Chunks not matching the filter are dimmed.
+    filter: Option<filter::Filter>,
+    /// Address to center the view on at the next frame, once the size of the view is known.
+    jump_to: Option<u64>,
+    /// Byte picked with `go_to`, outlined until the canvas is clicked.
+    highlighted_ptr: Option<u64>,
+    /// Where the canvas was on the screen in the last frame.
+    view_rect: Option<egui::Rect>,
+    gridlines: Gridlines,
+    /// Layout of the map only. The address index of `Chunks` doesn't depend on it.
+    layout: Layout,
+    bytes_per_line: u64,
+    byte_width_px: u64,
+    /// Bumped whenever how chunks are painted changes without any event: hidden chunks, filter,
+    /// colours or anomaly policies.
+    paint_generation: usize,
+    /// Density texture and the event count, paint generation and layout it was made for.
+    density: Option<(usize, usize, Layout, u64, egui::TextureHandle)>,
 }
 
 impl Memory {
@@ -25,11 +262,30 @@ impl Memory {
             translation: egui::Vec2::ZERO,
             scale: 0.5,
             selected_ptr: None,
+            selection: BTreeSet::new(),
+            box_start: None,
+            hidden: HashSet::new(),
+            pinned: BTreeSet::new(),
+            prediction: None,
+            simulation: None,
+            color_by: ColorBy::State,
+            filter: None,
+            jump_to: None,
+            highlighted_ptr: None,
+            view_rect: None,
+            gridlines: Gridlines::None,
+            layout: Layout::Rows,
+            bytes_per_line: DEFAULT_BYTES_PER_LINE,
+            byte_width_px: DEFAULT_BYTE_WIDTH_PX,
+            paint_generation: 0,
+            density: None,
         }
     }
 
     pub fn update_transform(&mut self, response: &egui::Response, zoom: f32) {
-        self.translation += response.drag_delta();
+        if self.box_start.is_none() {
+            self.translation += response.drag_delta();
+        }
 
         // If the zoom is 1.0, we don't need to do everything else after.
         if zoom == 1.0 || !response.hovered() {
@@ -55,49 +311,405 @@ impl Memory {
         (egui::Pos2::new(x, y) * self.scale) + self.translation
     }
 
-    fn size(&self, size: f32) -> f32 {
-        size * self.scale
+    /// Converts a rect of the canvas into a rect on the screen.
+    fn to_screen(&self, rect: egui::Rect) -> egui::Rect {
+        egui::Rect::from_min_size(self.position(rect.min.x, rect.min.y), rect.size() * self.scale)
+    }
+
+    /// Converts a position on the screen into a position on the canvas.
+    fn to_canvas(&self, pos: egui::Pos2) -> egui::Pos2 {
+        (pos - self.translation) / self.scale
     }
 
     fn rect_from_x_y_size(&self, x: u64, y: u64, size: u64) -> egui::Rect {
-        let position = self.position((x * BYTE_WIDTH_PX) as f32, (y * LINE_HEIGHT_PX) as f32);
-        let size = egui::Vec2::new(self.size((size * BYTE_WIDTH_PX) as f32),
-            self.size(LINE_HEIGHT_PX as f32));
+        let position = egui::Pos2::new((x * self.byte_width_px) as f32,
+            (y * LINE_HEIGHT_PX) as f32);
+        let size = egui::Vec2::new((size * self.byte_width_px) as f32, LINE_HEIGHT_PX as f32);
         egui::Rect::from_min_size(position, size)
     }
 
     fn rect_from_ptr_and_size(&self, ptr: u64, size: u64) -> egui::Rect {
-        let position = self.position(((ptr % BYTES_PER_LINE) * BYTE_WIDTH_PX) as f32,
-            ((ptr / BYTES_PER_LINE) * LINE_HEIGHT_PX) as f32);
-        let size = egui::Vec2::new(self.size((size * BYTE_WIDTH_PX) as f32),
-            self.size(LINE_HEIGHT_PX as f32));
-        egui::Rect::from_min_size(position, size)
+        self.rect_from_x_y_size(ptr % self.bytes_per_line, ptr / self.bytes_per_line, size)
     }
 
-    fn chunk_to_rects(&self, chunk: &alloc::Chunk) -> (egui::Color32, Vec<egui::Rect>) {
-        let start_x = chunk.ptr % BYTES_PER_LINE;
+    /// Rects covering a range of memory on the canvas, before the view is panned and zoomed.
+    pub fn canvas_rects(&self, ptr: u64, size: u64) -> Vec<egui::Rect> {
+        match self.layout {
+            Layout::Rows => self.row_rects(ptr, size),
+            Layout::Hilbert => self.hilbert_squares(ptr, size)
+                .into_iter()
+                .map(|square| self.square_rect(square))
+                .collect(),
+        }
+    }
+
+    /// Side of the square the Hilbert curve fills, in bytes.
+    fn hilbert_side(&self) -> u64 {
+        alloc::HEAP_SIZE.isqrt()
+    }
+
+    /// Squares of cells covering a range of memory with the Hilbert layout. The part of the range
+    /// outside the heap is left out.
+    fn hilbert_squares(&self, ptr: u64, size: u64) -> Vec<hilbert::Square> {
+        let end = ptr.saturating_add(size).min(alloc::HEAP_SIZE);
+        hilbert::squares(self.hilbert_side(), ptr.min(end)..end)
+    }
+
+    fn square_rect(&self, square: hilbert::Square) -> egui::Rect {
+        let cell = self.byte_width_px as f32;
+        egui::Rect::from_min_size(egui::Pos2::new(square.x as f32, square.y as f32) * cell,
+            egui::Vec2::splat(square.side as f32 * cell))
+    }
+
+    /// Rects covering a range of memory with the row layout, one per line it spans. The part of the
+    /// range outside the heap is left out.
+    fn row_rects(&self, ptr: u64, size: u64) -> Vec<egui::Rect> {
+        let end = ptr.saturating_add(size).min(alloc::HEAP_SIZE);
+        let ptr = ptr.min(end);
+        let size = end - ptr;
+        let start_x = ptr % self.bytes_per_line;
         let mut rects = Vec::new();
-        let color = chunk.state.to_color();
-        if start_x + chunk.size < BYTES_PER_LINE {
-            return (color, vec![self.rect_from_ptr_and_size(chunk.ptr, chunk.size)]);
+        if start_x + size < self.bytes_per_line {
+            return vec![self.rect_from_ptr_and_size(ptr, size)];
         } else {
-            rects.push(self.rect_from_ptr_and_size(chunk.ptr, BYTES_PER_LINE - start_x));
+            rects.push(self.rect_from_ptr_and_size(ptr, self.bytes_per_line - start_x));
         }
-        if chunk.size < BYTES_PER_LINE - start_x {
-            return (color, rects);
+        if size < self.bytes_per_line - start_x {
+            return rects;
         }
-        let mut remaining = chunk.size - (BYTES_PER_LINE - start_x);
-        let mut y = chunk.ptr / BYTES_PER_LINE + 1;
+        let mut remaining = size - (self.bytes_per_line - start_x);
+        let mut y = ptr / self.bytes_per_line + 1;
         loop {
-            if remaining < BYTES_PER_LINE {
-                rects.push(self.rect_from_x_y_size(0, y, remaining));
+            if remaining < self.bytes_per_line {
+                if remaining > 0 {
+                    rects.push(self.rect_from_x_y_size(0, y, remaining));
+                }
                 break;
             }
-            rects.push(self.rect_from_x_y_size(0, y, BYTES_PER_LINE));
+            rects.push(self.rect_from_x_y_size(0, y, self.bytes_per_line));
             y += 1;
-            remaining -= BYTES_PER_LINE;
+            remaining -= self.bytes_per_line;
+        }
+        rects
+    }
+
+    fn range_to_rects(&self, ptr: u64, size: u64) -> Vec<egui::Rect> {
+        self.canvas_rects(ptr, size)
+            .into_iter()
+            .map(|rect| self.to_screen(rect))
+            .collect()
+    }
+
+    fn line_count(&self) -> u64 {
+        alloc::HEAP_SIZE.div_ceil(self.bytes_per_line)
+    }
+
+    pub fn bytes_per_line(&self) -> u64 {
+        self.bytes_per_line
+    }
+
+    pub fn byte_width_px(&self) -> u64 {
+        self.byte_width_px
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Changes the layout while keeping the address at the center of the view in place.
+    pub fn set_layout(&mut self, layout: Layout, bytes_per_line: u64, byte_width_px: u64) {
+        let center = self.viewport().and_then(|viewport| self.ptr_at(viewport.center()));
+        self.layout = layout;
+        self.bytes_per_line = bytes_per_line;
+        self.byte_width_px = byte_width_px;
+        if let Some(ptr) = center {
+            self.jump_to = Some(ptr);
+        }
+    }
+
+    /// Size of the whole memory map on the canvas.
+    pub fn canvas_size(&self) -> egui::Vec2 {
+        if self.layout == Layout::Hilbert {
+            return egui::Vec2::splat((self.hilbert_side() * self.byte_width_px) as f32);
+        }
+        egui::Vec2::new((self.bytes_per_line * self.byte_width_px) as f32,
+            (LINE_HEIGHT_PX * self.line_count()) as f32)
+    }
+
+    /// Address of the byte at a position of the canvas, if it is inside the memory map.
+    pub fn ptr_at(&self, pos: egui::Pos2) -> Option<u64> {
+        if pos.x < 0.0 || pos.y < 0.0 {
+            return None;
+        }
+        if self.layout == Layout::Hilbert {
+            let side = self.hilbert_side();
+            let x = pos.x as u64 / self.byte_width_px;
+            let y = pos.y as u64 / self.byte_width_px;
+            return match x < side && y < side {
+                true => Some(hilbert::xy2d(side, x, y)),
+                false => None,
+            };
+        }
+        let x = pos.x as u64 / self.byte_width_px;
+        let y = pos.y as u64 / LINE_HEIGHT_PX;
+        match x < self.bytes_per_line && y < self.line_count() {
+            true => Some(y * self.bytes_per_line + x),
+            false => None,
+        }
+    }
+
+    /// Index buckets with a part inside `rect` on the screen, in order.
+    fn visible_buckets(&self, rect: egui::Rect) -> Vec<usize> {
+        (0..alloc::BUCKET_COUNT)
+            .filter(|&idx| {
+                self.range_to_rects(idx as u64 * alloc::BUCKET_SIZE, alloc::BUCKET_SIZE)
+                    .iter()
+                    .any(|bucket| bucket.intersects(rect))
+            })
+            .collect()
+    }
+
+    /// Live bytes of every block of `DENSITY_BLOCK_SIZE` bytes, one texel per block laid out like
+    /// the canvas, in the colour of the chunk with the most bytes in the block. Blocks holding a
+    /// chunk in a bad state or a painted anomaly take its colour, and blocks without live bytes in
+    /// the free list are drawn as free blocks. Hidden chunks are left out.
+    fn density_image(&self) -> egui::ColorImage {
+        let side = self.hilbert_side();
+        let block_side = DENSITY_BLOCK_SIZE.isqrt();
+        let (width, height) = match self.layout {
+            Layout::Rows => (self.bytes_per_line / DENSITY_BLOCK_SIZE, self.line_count()),
+            Layout::Hilbert => (side / block_side, side / block_side),
+        };
+        let texel = |ptr: u64| -> usize {
+            let (x, y) = match self.layout {
+                Layout::Rows => {
+                    ((ptr % self.bytes_per_line) / DENSITY_BLOCK_SIZE, ptr / self.bytes_per_line)
+                }
+                Layout::Hilbert => {
+                    let (x, y) = hilbert::d2xy(side, ptr);
+                    (x / block_side, y / block_side)
+                }
+            };
+            (y * width + x) as usize
+        };
+        let mut bytes = vec![0; (width * height) as usize];
+        let mut fills = vec![(0, COLOR_USED); (width * height) as usize];
+        let mut colors = vec![None; (width * height) as usize];
+        let mut is_free = vec![false; (width * height) as usize];
+        for chunk in self.chunks.iter().map(|(_, chunk)| chunk) {
+            let Some(color) = self.painted_color(chunk) else {
+                continue;
+            };
+            for block in density_blocks(chunk.ptr, chunk.size) {
+                let idx = texel(block.start);
+                let len = block.end - block.start;
+                if !chunk.is_bad_free() {
+                    bytes[idx] += len;
+                    if len > fills[idx].0 {
+                        fills[idx] = (len, color);
+                    }
+                }
+                if chunk.state != alloc::ChunkState::Ok {
+                    colors[idx] = Some(color);
+                }
+            }
+        }
+        for anomaly in self.painted_anomalies() {
+            for block in density_blocks(anomaly.ptr, anomaly.len) {
+                colors[texel(block.start)] = Some(anomaly.kind.to_color());
+            }
+        }
+        for free_block in self.chunks.free_list() {
+            for block in density_blocks(free_block.start, free_block.end - free_block.start) {
+                is_free[texel(block.start)] = true;
+            }
+        }
+        let pixels = bytes.into_iter()
+            .zip(fills)
+            .zip(colors)
+            .zip(is_free)
+            .map(|(((bytes, (_, fill)), color), is_free)| color.unwrap_or_else(|| {
+                match (bytes, is_free) {
+                    (0, true) => COLOR_FREE_BLOCK.gamma_multiply(0.5),
+                    _ => fill.gamma_multiply((bytes as f32 / DENSITY_BLOCK_SIZE as f32).min(1.0)),
+                }
+            }))
+            .collect();
+        egui::ColorImage {
+            size: [width as usize, height as usize],
+            pixels,
+        }
+    }
+
+    /// Anomalies with a known length whose policy lets them be painted.
+    pub fn painted_anomalies(&self) -> impl Iterator<Item = &alloc::Anomaly> {
+        self.chunks.anomalies()
+            .iter()
+            .filter(|anomaly| anomaly.len > 0)
+            .filter(|anomaly| self.chunks.policy(anomaly.kind) != alloc::AnomalyPolicy::Log)
+    }
+
+    /// Texture of the live bytes of the whole map, remade when an event was applied or the layout
+    /// changed since the last call. It covers the canvas from `(0, 0)` to `canvas_size`.
+    pub fn density_texture(&mut self, ctx: &egui::Context) -> egui::TextureId {
+        let event_count = self.chunks.event_count();
+        match &self.density {
+            Some((count, generation, layout, bytes_per_line, texture)) if *count == event_count
+                && *generation == self.paint_generation && *layout == self.layout
+                && *bytes_per_line == self.bytes_per_line => {
+                texture.id()
+            }
+            _ => {
+                let texture = ctx.load_texture("density", self.density_image(),
+                    egui::TextureOptions::NEAREST);
+                let id = texture.id();
+                self.density = Some((event_count, self.paint_generation, self.layout,
+                    self.bytes_per_line, texture));
+                id
+            }
+        }
+    }
+
+    /// Part of the canvas shown in the last frame.
+    pub fn viewport(&self) -> Option<egui::Rect> {
+        let rect = self.view_rect?;
+        Some(egui::Rect::from_min_max(self.to_canvas(rect.min), self.to_canvas(rect.max)))
+    }
+
+    /// Lines at every multiple of the gridline spacing. A spacing dividing the row width gives
+    /// columns, one that is a multiple of it gives rows.
+    fn paint_gridlines(&self, painter: &egui::Painter) {
+        let Some(spacing) = self.gridlines.spacing() else {
+            return;
+        };
+        let map = self.to_screen(egui::Rect::from_min_size(egui::Pos2::ZERO, self.canvas_size()));
+        let stroke = egui::Stroke::new(1.0, COLOR_GRIDLINE);
+        if self.layout == Layout::Hilbert {
+            // Aligned runs of 4^k bytes fill squares of 2^k cells, so both spacings give a grid.
+            let cells = spacing.isqrt();
+            let step_px = (cells * self.byte_width_px) as f32;
+            if step_px * self.scale < MIN_GRIDLINE_SPACING_PX {
+                return;
+            }
+            for idx in 1..self.hilbert_side() / cells {
+                let position = self.position(idx as f32 * step_px, idx as f32 * step_px);
+                painter.vline(position.x, map.y_range(), stroke);
+                painter.hline(map.x_range(), position.y, stroke);
+            }
+            return;
+        }
+        if spacing < self.bytes_per_line && self.bytes_per_line.is_multiple_of(spacing) {
+            if (spacing * self.byte_width_px) as f32 * self.scale < MIN_GRIDLINE_SPACING_PX {
+                return;
+            }
+            for column in (spacing..self.bytes_per_line).step_by(spacing as usize) {
+                let x = self.position((column * self.byte_width_px) as f32, 0.0).x;
+                painter.vline(x, map.y_range(), stroke);
+            }
+        } else if spacing.is_multiple_of(self.bytes_per_line) {
+            let rows = spacing / self.bytes_per_line;
+            if (rows * LINE_HEIGHT_PX) as f32 * self.scale < MIN_GRIDLINE_SPACING_PX {
+                return;
+            }
+            for row in (rows..self.line_count()).step_by(rows as usize) {
+                let y = self.position(0.0, (row * LINE_HEIGHT_PX) as f32).y;
+                painter.hline(map.x_range(), y, stroke);
+            }
+        }
+    }
+
+    /// Address of the visible rows along the left edge and byte offsets within a row along the
+    /// top edge. Labels are spread out more as the view zooms out.
+    fn paint_ruler(&self, painter: &egui::Painter, rect: egui::Rect) {
+        if self.layout == Layout::Hilbert {
+            return;
+        }
+        let font = egui::FontId::monospace(RULER_FONT_SIZE);
+        let viewport = egui::Rect::from_min_max(self.to_canvas(rect.min), self.to_canvas(rect.max));
+        painter.rect_filled(egui::Rect::from_min_size(rect.min,
+            egui::Vec2::new(RULER_WIDTH_PX, rect.height())), 0.0, COLOR_RULER_BACKGROUND);
+        painter.rect_filled(egui::Rect::from_min_size(rect.min,
+            egui::Vec2::new(rect.width(), RULER_HEIGHT_PX)), 0.0, COLOR_RULER_BACKGROUND);
+
+        let row_step = label_step(LINE_HEIGHT_PX as f32 * self.scale, MIN_ROW_LABEL_SPACING_PX);
+        let first_row = viewport.min.y.max(0.0) as u64 / LINE_HEIGHT_PX / row_step * row_step;
+        let last_row = (viewport.max.y.max(0.0) as u64 / LINE_HEIGHT_PX + 1).min(self.line_count());
+        for row in (first_row..last_row).step_by(row_step as usize) {
+            let y = self.position(0.0, (row * LINE_HEIGHT_PX + LINE_HEIGHT_PX / 2) as f32).y;
+            if y < rect.top() + RULER_HEIGHT_PX {
+                continue;
+            }
+            painter.text(egui::Pos2::new(rect.left() + RULER_PADDING_PX, y),
+                egui::Align2::LEFT_CENTER, format!("{:#07x}", row * self.bytes_per_line),
+                font.clone(), COLOR_RULER);
+        }
+
+        let column_step = label_step(self.byte_width_px as f32 * self.scale,
+            MIN_COLUMN_LABEL_SPACING_PX);
+        let first_column = viewport.min.x.max(0.0) as u64 / self.byte_width_px / column_step
+            * column_step;
+        let last_column = (viewport.max.x.max(0.0) as u64 / self.byte_width_px + 1)
+            .min(self.bytes_per_line);
+        let stroke = egui::Stroke::new(1.0, COLOR_RULER);
+        for column in (first_column..last_column).step_by(column_step as usize) {
+            let x = self.position((column * self.byte_width_px) as f32, 0.0).x;
+            if x < rect.left() + RULER_WIDTH_PX {
+                continue;
+            }
+            painter.vline(x, rect.top()..=rect.top() + RULER_HEIGHT_PX, stroke);
+            painter.text(egui::Pos2::new(x + RULER_PADDING_PX, rect.top() + RULER_PADDING_PX),
+                egui::Align2::LEFT_TOP, format!("+{:#x}", column), font.clone(), COLOR_RULER);
+        }
+    }
+
+    /// Outlines a selected or pinned chunk. With the Hilbert layout a chunk is made of squares of
+    /// different sizes, so only the border of their union is drawn.
+    fn paint_selection(&self, painter: &egui::Painter, chunk: &alloc::Chunk,
+        rects: &[egui::Rect], color: egui::Color32) {
+        let stroke = egui::Stroke::new(1.0, color);
+        if self.layout == Layout::Rows {
+            for rect in rects {
+                painter.rect_stroke(*rect, 3.0, stroke);
+            }
+            return;
+        }
+        let cell = self.byte_width_px as f32;
+        for edge in hilbert::outline(&self.hilbert_squares(chunk.ptr, chunk.size)) {
+            let start = self.position(edge.x as f32 * cell, edge.y as f32 * cell);
+            let end = match edge.is_horizontal {
+                true => start + egui::Vec2::new(cell * self.scale, 0.0),
+                false => start + egui::Vec2::new(0.0, cell * self.scale),
+            };
+            painter.line_segment([start, end], stroke);
+        }
+    }
+
+    fn chunk_color(&self, chunk: &alloc::Chunk) -> egui::Color32 {
+        if chunk.state != alloc::ChunkState::Ok {
+            return chunk.state.to_color();
+        }
+        match self.color_by {
+            ColorBy::State => chunk.state.to_color(),
+            ColorBy::Identifier => identifier_color(&chunk.identifier),
+            ColorBy::Age => {
+                age_color(self.chunks.event_count().saturating_sub(chunk.allocated_event))
+            }
+            ColorBy::Size => size_color(chunk.size),
+            ColorBy::Thread => chunk.thread.map_or(chunk.state.to_color(), thread_color),
+        }
+    }
+
+    /// Colour of a chunk as painted in the views, dimmed when it does not match the filter. Hidden
+    /// chunks are not painted.
+    pub fn painted_color(&self, chunk: &alloc::Chunk) -> Option<egui::Color32> {
+        if self.hidden.contains(&chunk.key()) {
+            return None;
+        }
+        let color = self.chunk_color(chunk);
+        match self.filter.as_ref().is_some_and(|filter| !filter.matches(chunk)) {
+            true => Some(color.gamma_multiply(0.2)),
+            false => Some(color),
         }
-        (color, rects)
     }
 
     fn is_cell_hovered(&self, maybe_cursor: Option<egui::Pos2>, rects: &[egui::Rect]) -> bool {
@@ -107,6 +719,104 @@ impl Memory {
         return false;
     }
 
+    pub fn chunks(&self) -> &alloc::Chunks {
+        &self.chunks
+    }
+
+    pub fn set_prediction(&mut self, prediction: Option<ops::Range<u64>>) {
+        self.prediction = prediction;
+    }
+
+    pub fn simulation(&self) -> Option<&simulate::Simulation> {
+        self.simulation.as_ref()
+    }
+
+    pub fn set_simulation(&mut self, simulation: Option<simulate::Simulation>) {
+        self.simulation = simulation;
+    }
+
+    pub fn color_by(&self) -> ColorBy {
+        self.color_by
+    }
+
+    pub fn set_color_by(&mut self, color_by: ColorBy) {
+        self.color_by = color_by;
+        self.paint_generation += 1;
+    }
+
+    pub fn gridlines(&self) -> Gridlines {
+        self.gridlines
+    }
+
+    pub fn set_gridlines(&mut self, gridlines: Gridlines) {
+        self.gridlines = gridlines;
+    }
+
+    pub fn set_filter(&mut self, filter: Option<filter::Filter>) {
+        self.filter = filter;
+        self.paint_generation += 1;
+    }
+
+    /// Centers the view on the match with the lowest address and selects it.
+    pub fn jump_to_first_match(&mut self) {
+        let Some(filter) = &self.filter else {
+            return;
+        };
+        let first = self.chunks
+            .iter()
+            .filter(|(_, chunk)| filter.matches(chunk))
+            .map(|(&ptr, _)| ptr)
+            .min();
+        if let Some(ptr) = first {
+            self.select(Some(ptr));
+            self.jump_to = Some(ptr);
+        }
+    }
+
+    /// Centers the view on `ptr` at the next frame.
+    pub fn center_on_ptr(&mut self, ptr: u64) {
+        self.jump_to = Some(ptr);
+    }
+
+    /// Centers the view on `ptr`, outlines the byte and selects the chunk holding it.
+    pub fn go_to(&mut self, ptr: u64) {
+        self.jump_to = Some(ptr);
+        self.highlighted_ptr = Some(ptr);
+        self.select(self.chunks.containing(ptr).map(|chunk| chunk.ptr));
+    }
+
+    /// Moves the view so the byte at `ptr` is at the center of `rect`.
+    fn center_on(&mut self, ptr: u64, rect: egui::Rect) {
+        let Some(center) = self.canvas_rects(ptr, 1).first().map(|rect| rect.center()) else {
+            return;
+        };
+        self.translation = rect.center().to_vec2() - center.to_vec2() * self.scale;
+    }
+
+    /// Processes the events received since the last frame. Chunks that are gone are no longer
+    /// selected nor hidden, pinned chunks stay listed as freed.
+    pub fn update(&mut self) {
+        if self.chunks.update() {
+            let chunks = &self.chunks;
+            self.selection.retain(|&key| Self::get_by_key(chunks, key).is_some());
+            self.hidden.retain(|&key| Self::get_by_key(chunks, key).is_some());
+        }
+    }
+
+    fn get_by_key(chunks: &alloc::Chunks, (ptr, allocated_event): ChunkKey)
+        -> Option<&alloc::Chunk> {
+        chunks.get(ptr).filter(|chunk| chunk.allocated_event == allocated_event)
+    }
+
+    pub fn set_heap(&mut self, heap: ops::Range<u64>) {
+        self.chunks.set_heap(heap);
+    }
+
+    /// Address shown in the side panel, kept once its chunk is freed.
+    pub fn selected_ptr(&self) -> Option<u64> {
+        self.selected_ptr
+    }
+
     pub fn selected_chunk(&self) -> Option<alloc::Chunk> {
         if let Some(ptr) = self.selected_ptr {
             return Some((*self.chunks.get(ptr)?).clone());
@@ -114,53 +824,400 @@ impl Memory {
         return None;
     }
 
+    /// Selects only the chunk at `ptr`, or nothing.
+    pub fn select(&mut self, ptr: Option<u64>) {
+        self.selected_ptr = ptr;
+        self.selection = ptr
+            .and_then(|ptr| self.chunks.get(ptr))
+            .map(|chunk| chunk.key())
+            .into_iter()
+            .collect();
+    }
+
+    /// Adds the chunk at `ptr` to the selection, or removes it if it is already in.
+    fn toggle_selected(&mut self, ptr: u64) {
+        let Some(key) = self.chunks.get(ptr).map(|chunk| chunk.key()) else {
+            return;
+        };
+        if self.selection.remove(&key) {
+            if self.selected_ptr == Some(ptr) {
+                self.selected_ptr = self.selection.first().map(|&(ptr, _)| ptr);
+            }
+        } else {
+            self.selection.insert(key);
+            self.selected_ptr = Some(ptr);
+        }
+    }
+
+    /// Adds the chunks with a part inside `rect` on the screen to the selection.
+    fn select_in(&mut self, rect: egui::Rect) {
+        let buckets = self.visible_buckets(rect);
+        let keys = self.chunks.in_buckets(&buckets)
+            .filter(|chunk| !self.hidden.contains(&chunk.key()))
+            .filter(|chunk| {
+                self.range_to_rects(chunk.ptr, chunk.size)
+                    .iter()
+                    .any(|chunk_rect| chunk_rect.intersects(rect))
+            })
+            .map(|chunk| chunk.key())
+            .collect::<Vec<ChunkKey>>();
+        self.selection.extend(keys);
+        if self.selected_ptr.is_none() {
+            self.selected_ptr = self.selection.first().map(|&(ptr, _)| ptr);
+        }
+    }
+
+    /// Selected chunks that are still alive, by address.
+    pub fn selected_chunks(&self) -> Vec<&alloc::Chunk> {
+        self.selection
+            .iter()
+            .filter_map(|&key| Self::get_by_key(&self.chunks, key))
+            .collect()
+    }
+
+    /// Adds every chunk sharing an identifier with a selected chunk to the selection.
+    pub fn select_same_identifiers(&mut self) {
+        let identifiers = self.selected_chunks()
+            .into_iter()
+            .map(|chunk| chunk.identifier.as_str())
+            .collect::<HashSet<&str>>();
+        let keys = self.chunks
+            .iter()
+            .map(|(_, chunk)| chunk)
+            .filter(|chunk| {
+                identifiers.contains(chunk.identifier.as_str())
+                    && !self.hidden.contains(&chunk.key())
+            })
+            .map(|chunk| chunk.key())
+            .collect::<Vec<ChunkKey>>();
+        self.selection.extend(keys);
+    }
+
+    /// The selected chunks as CSV, one chunk per line.
+    pub fn selection_report(&self) -> String {
+        let mut report = String::from("ptr,size,identifier,state\n");
+        for chunk in self.selected_chunks() {
+            report += &format!("{:#01x},{},{},{}\n", chunk.ptr, chunk.size, chunk.identifier,
+                chunk.state);
+        }
+        report
+    }
+
+    /// Stops drawing the selected chunks and clears the selection.
+    pub fn hide_selection(&mut self) {
+        self.hidden.extend(self.selection.iter().copied());
+        self.select(None);
+        self.paint_generation += 1;
+    }
+
+    pub fn hidden_count(&self) -> usize {
+        self.hidden.len()
+    }
+
+    pub fn show_hidden(&mut self) {
+        self.hidden.clear();
+        self.paint_generation += 1;
+    }
+
+    /// Pinned chunks by address, with the chunk while it lives.
+    pub fn pinned(&self) -> Vec<(ChunkKey, Option<&alloc::Chunk>)> {
+        self.pinned
+            .iter()
+            .map(|&key| (key, Self::get_by_key(&self.chunks, key)))
+            .collect()
+    }
+
+    pub fn pin_selection(&mut self) {
+        self.pinned.extend(self.selection.iter().copied());
+    }
+
+    pub fn unpin(&mut self, key: ChunkKey) {
+        self.pinned.remove(&key);
+    }
+
+    pub fn anomalies(&self) -> &[alloc::Anomaly] {
+        self.chunks.anomalies()
+    }
+
+    pub fn quarantine_limit(&self) -> alloc::QuarantineLimit {
+        self.chunks.quarantine_limit()
+    }
+
+    pub fn set_quarantine_limit(&mut self, limit: alloc::QuarantineLimit) {
+        self.chunks.set_quarantine_limit(limit);
+    }
+
+    pub fn breakpoints(&self) -> &[breakpoint::Breakpoint] {
+        self.chunks.breakpoints()
+    }
+
+    pub fn add_breakpoint(&mut self, condition: breakpoint::Condition) {
+        self.chunks.add_breakpoint(condition);
+    }
+
+    pub fn remove_breakpoint(&mut self, idx: usize) {
+        self.chunks.remove_breakpoint(idx);
+    }
+
+    pub fn set_breakpoint_enabled(&mut self, idx: usize, is_enabled: bool) {
+        self.chunks.set_breakpoint_enabled(idx, is_enabled);
+    }
+
+    pub fn fired_breakpoint(&self) -> Option<&breakpoint::Breakpoint> {
+        self.chunks.fired_breakpoint()
+    }
+
     pub fn set_do_advance(&mut self, do_advance: bool) {
-        self.chunks.do_advance = do_advance;
+        self.chunks.set_do_advance(do_advance);
     }
 
     pub fn do_advance(&self) -> bool {
-        self.chunks.do_advance
+        self.chunks.do_advance()
+    }
+
+    pub fn policy(&self, kind: alloc::AnomalyKind) -> alloc::AnomalyPolicy {
+        self.chunks.policy(kind)
+    }
+
+    pub fn set_policy(&mut self, kind: alloc::AnomalyKind, policy: alloc::AnomalyPolicy) {
+        self.chunks.set_policy(kind, policy);
+        self.paint_generation += 1;
     }
 }
 
 impl egui::Widget for &mut Memory {
     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
-        self.chunks.update();
-        let (response, painter) = ui.allocate_painter(ui.available_size(),
+        let (mut response, painter) = ui.allocate_painter(ui.available_size(),
             egui::Sense::click_and_drag());
+        let is_shift = ui.input(|i| i.modifiers.shift);
+        if response.drag_started() && is_shift {
+            self.box_start = response.interact_pointer_pos().map(|pos| self.to_canvas(pos));
+        }
         self.update_transform(&response, ui.input(|i| i.zoom_delta()));
+        if let Some(ptr) = self.jump_to.take() {
+            self.center_on(ptr, response.rect);
+        }
+        self.view_rect = Some(response.rect);
+
+        let visible_buckets = self.visible_buckets(response.rect);
+        let is_aggregated = self.byte_width_px as f32 * self.scale < MAX_DENSITY_BYTE_PX
+            && self.chunks.in_buckets(&visible_buckets).nth(MIN_DENSITY_CHUNK_COUNT).is_some();
+        let density = is_aggregated.then(|| self.density_texture(ui.ctx()));
 
         let clicked = response.clicked();
         let maybe_hover_pos = ui.ctx().input(|i| i.pointer.hover_pos());
 
-        let mut did_select_cell = false;
-        for (&chunk_ptr, chunk) in self.chunks.iter() {
-            let (mut color, rects) = self.chunk_to_rects(chunk);
+        // Only what is in view is drawn. Once aggregated, the density texture covers everything but
+        // the outlines, which are left out.
+        let detailed_buckets: &[usize] = match is_aggregated {
+            true => &[],
+            false => &visible_buckets,
+        };
+        // Free blocks and anomalies come from the tracked process and can span the whole heap, only
+        // their part in view is drawn.
+        let detailed_ranges = visible_ranges(detailed_buckets);
+
+        // Freed chunks are drawn first, as outlines, so live chunks reusing the space cover them.
+        let mut hovered_ghost = None;
+        for chunk in self.chunks.quarantine()
+            .filter(|chunk| is_range_visible(detailed_buckets, chunk.ptr, chunk.size)) {
+            let rects = self.range_to_rects(chunk.ptr, chunk.size);
+            if self.is_cell_hovered(maybe_hover_pos, &rects) {
+                hovered_ghost = Some(chunk);
+            }
+            let color = match chunk.is_cross_thread_free() {
+                true => COLOR_CROSS_THREAD,
+                false => COLOR_QUARANTINE,
+            };
+            for rect in rects {
+                painter.rect_stroke(rect, 3.0, egui::Stroke::new(1.0, color));
+            }
+        }
+
+        for block in self.chunks.free_list()
+            .iter()
+            .flat_map(|block| visible_parts(&detailed_ranges, block.clone())) {
+            for rect in self.range_to_rects(block.start, block.end - block.start) {
+                painter.rect_filled(rect, 3.0, COLOR_FREE_BLOCK.gamma_multiply(0.5));
+            }
+        }
+
+        let mut clicked_ptr = None;
+        let mut did_hover_cell = false;
+        if let Some(texture) = density {
+            let uv = egui::Rect::from_min_max(egui::Pos2::ZERO, egui::Pos2::new(1.0, 1.0));
+            let map = self.to_screen(egui::Rect::from_min_size(egui::Pos2::ZERO,
+                self.canvas_size()));
+            painter.image(texture, map, uv, egui::Color32::WHITE);
+            if let (true, Some(pos)) = (clicked, maybe_hover_pos) {
+                clicked_ptr = self.ptr_at(self.to_canvas(pos))
+                    .and_then(|ptr| self.chunks.containing(ptr))
+                    .map(|chunk| chunk.ptr);
+            }
+            for chunk in self.selected_chunks() {
+                self.paint_selection(&painter, chunk, &self.range_to_rects(chunk.ptr, chunk.size),
+                    COLOR_SELECTED);
+            }
+        }
+        for (chunk, mut color) in self.chunks.in_buckets(detailed_buckets)
+            .filter_map(|chunk| Some((chunk, self.painted_color(chunk)?))) {
+            let chunk_ptr = chunk.ptr;
+            let rects = self.range_to_rects(chunk.ptr, chunk.size);
             if self.is_cell_hovered(maybe_hover_pos, &rects) {
                 color = COLOR_HOVERD;
+                did_hover_cell = true;
                 if clicked {
-                    did_select_cell = true;
-                    self.selected_ptr = Some(chunk_ptr);
+                    clicked_ptr = Some(chunk_ptr);
                 }
             }
-            let is_selected = self.selected_ptr.map_or(false, |ptr| ptr == chunk_ptr);
-            for rect in rects {
-                painter.rect_filled(rect, 3.0, color);
-                if is_selected {
-                    painter.rect_stroke(rect, 3.0, egui::Stroke::new(1.0, COLOR_SELECTED));
+            for rect in rects.iter() {
+                painter.rect_filled(*rect, 3.0, color);
+            }
+            if self.pinned.contains(&chunk.key()) {
+                self.paint_selection(&painter, chunk, &rects, COLOR_PINNED);
+            }
+            if self.selection.contains(&chunk.key()) {
+                self.paint_selection(&painter, chunk, &rects, COLOR_SELECTED);
+            }
+        }
+        for anomaly in self.painted_anomalies() {
+            let range = anomaly.ptr..anomaly.ptr.saturating_add(anomaly.len);
+            for part in visible_parts(&detailed_ranges, range) {
+                for rect in self.range_to_rects(part.start, part.end - part.start) {
+                    painter.rect_filled(rect, 0.0, anomaly.kind.to_color());
                 }
             }
         }
-        if !did_select_cell && clicked {
-            self.selected_ptr = None;
+        // The largest gap can span the whole heap, only the part in view is outlined.
+        if let Some(gap) = self.chunks.fragmentation().largest.clone() {
+            let visible = visible_ranges(&visible_buckets);
+            for part in visible_parts(&visible, gap) {
+                for rect in self.range_to_rects(part.start, part.end - part.start) {
+                    painter.rect_stroke(rect, 0.0, egui::Stroke::new(1.0, COLOR_LARGEST_GAP));
+                }
+            }
+        }
+        if let Some(simulation) = &self.simulation {
+            let ranges = detailed_ranges
+                .iter()
+                .flat_map(|visible| simulation.ranges_in(visible.clone()));
+            for range in ranges {
+                for rect in self.range_to_rects(range.start, range.end - range.start) {
+                    painter.rect_stroke(rect, 3.0, egui::Stroke::new(1.0, COLOR_SIMULATION));
+                }
+            }
+        }
+        if let Some(prediction) = &self.prediction {
+            for rect in self.range_to_rects(prediction.start, prediction.end - prediction.start) {
+                painter.rect_filled(rect, 3.0, COLOR_PREDICTION.gamma_multiply(0.3));
+                painter.rect_stroke(rect, 3.0, egui::Stroke::new(1.0, COLOR_PREDICTION));
+            }
+        }
+        self.paint_gridlines(&painter);
+        if let Some(rect) = self.highlighted_ptr
+            .and_then(|ptr| self.range_to_rects(ptr, 1).first().copied()) {
+            painter.rect_stroke(rect.expand(HIGHLIGHT_MARGIN_PX), 0.0,
+                egui::Stroke::new(2.0, COLOR_HIGHLIGHTED));
+        }
+        // A click on a freed chunk selects its address, to look at its history.
+        let ghost_ptr = hovered_ghost.filter(|_| !did_hover_cell).map(|chunk| chunk.ptr);
+        if let (Some(chunk), false) = (hovered_ghost, did_hover_cell) {
+            let mut text = format!("freed {:#01x} ({} bytes)\nallocated by {}\nfreed by {}",
+                chunk.ptr, chunk.size, chunk.identifier, chunk.free_identifiers.join(", "));
+            if let (Some(thread), Some(free_thread)) = (chunk.thread, chunk.free_thread) {
+                text += &format!("\nthread {:x}, freed by thread {:x}", thread, free_thread);
+            }
+            response = response.on_hover_text_at_pointer(text);
+        }
+        if clicked {
+            self.highlighted_ptr = None;
+            match (clicked_ptr, is_shift) {
+                (Some(ptr), true) => self.toggle_selected(ptr),
+                (None, true) => {}
+                (ptr, false) => self.select(ptr.or(ghost_ptr)),
+            }
+        }
+        let box_rect = self.box_start
+            .zip(ui.input(|i| i.pointer.interact_pos()))
+            .map(|(start, pos)| egui::Rect::from_two_pos(self.position(start.x, start.y), pos));
+        if let Some(rect) = box_rect {
+            painter.rect(rect, 0.0, COLOR_SELECTED.gamma_multiply(0.1),
+                egui::Stroke::new(1.0, COLOR_SELECTED));
+        }
+        if response.drag_released() {
+            if let Some(rect) = box_rect {
+                self.select_in(rect);
+            }
+            self.box_start = None;
         }
 
-        let border_position = self.position(0.0, 0.0);
-        let border_size = egui::Vec2::new(self.size((BYTES_PER_LINE * BYTE_WIDTH_PX) as f32),
-            self.size((LINE_HEIGHT_PX * MAX_LINE_COUNT) as f32));
-        let border_rect = egui::Rect::from_min_size(border_position, border_size);
+        let border_rect = self.to_screen(egui::Rect::from_min_size(egui::Pos2::ZERO,
+            self.canvas_size()));
         painter.rect_stroke(border_rect, 0.0, egui::Stroke::new(1.0, egui::Color32::GRAY));
+        self.paint_ruler(&painter, response.rect);
 
         response
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{sync::mpsc, time};
+
+    /// A memory map of the chunks allocated by `lines`.
+    fn memory_from(lines: &[&str]) -> Memory {
+        let (tx, rx) = mpsc::channel();
+        for line in lines {
+            let timestamp = alloc::Timestamp::from_line(line, time::Duration::ZERO);
+            tx.send((alloc::Event::try_from_line(line).unwrap(), timestamp)).unwrap();
+        }
+        let mut memory = Memory::new(alloc::Chunks::from_receiver(rx));
+        memory.update();
+        memory
+    }
+
+    #[test]
+    fn ranges_are_drawn_up_to_the_end_of_the_heap() {
+        let mut memory = memory_from(&[]);
+        let rows = memory.canvas_rects(0x100, u64::MAX);
+        assert_eq!(rows.len() as u64, memory.line_count());
+        assert!(memory.canvas_rects(u64::MAX - 1, 0x10).iter().all(|rect| rect.width() == 0.0));
+        memory.set_layout(Layout::Hilbert, DEFAULT_BYTES_PER_LINE, DEFAULT_BYTE_WIDTH_PX);
+        assert!(memory.canvas_rects(u64::MAX - 1, 0x10).is_empty());
+        let side = memory.canvas_size().x;
+        assert!(memory.canvas_rects(0, u64::MAX).iter().all(|rect| rect.max.x <= side));
+    }
+
+    #[test]
+    fn visible_parts_are_cut_to_the_view() {
+        let visible = [0x400..0xc00, 0x1000..0x1400];
+        let parts = visible_parts(&visible, 0..u64::MAX).collect::<Vec<ops::Range<u64>>>();
+        assert!(parts == visible);
+        assert_eq!(visible_parts(&visible, 0xc00..0x1000).count(), 0);
+    }
+
+    #[test]
+    fn density_follows_hidden_chunks_filter_and_colours() {
+        let mut memory = memory_from(&["m:0,10,alloc_site", "m:10,8,other_site"]);
+        let texels = |memory: &Memory| {
+            let pixels = memory.density_image().pixels;
+            (pixels[0], pixels[1])
+        };
+        let (full, half) = texels(&memory);
+        assert!(full == COLOR_USED);
+        assert!(half == COLOR_USED.gamma_multiply(0.5));
+        memory.set_color_by(ColorBy::Identifier);
+        assert!(texels(&memory).0 == identifier_color("alloc_site"));
+        memory.set_filter(Some(filter::Filter {
+            identifier: Some(filter::IdentifierFilter::Substring(String::from("other"))),
+            ..Default::default()
+        }));
+        assert!(texels(&memory).0 == identifier_color("alloc_site").gamma_multiply(0.2));
+        let generation = memory.paint_generation;
+        memory.select(Some(0x10));
+        memory.hide_selection();
+        assert!(memory.paint_generation != generation);
+        assert!(texels(&memory).1 == egui::Color32::TRANSPARENT);
+    }
+}
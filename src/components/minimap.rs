@@ -0,0 +1,65 @@
+use crate::components::memory;
+
+const COLOR_BACKGROUND: egui::Color32 = egui::Color32::from_gray(27);
+const COLOR_VIEWPORT: egui::Color32 = egui::Color32::WHITE;
+/// Chunks are drawn at least this wide so small ones stay visible.
+const MIN_CHUNK_WIDTH_PX: f32 = 1.0;
+
+/// The whole memory map scaled down to a strip. Shows the part of the map in view and the
+/// anomalies. Clicking or dragging on it moves the view there.
+pub struct Minimap<'a> {
+    memory: &'a mut memory::Memory,
+}
+
+impl<'a> Minimap<'a> {
+    pub fn new(memory: &'a mut memory::Memory) -> Self {
+        Self {
+            memory,
+        }
+    }
+}
+
+impl egui::Widget for Minimap<'_> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let canvas_size = self.memory.canvas_size();
+        let available = ui.available_size();
+        let scale = (available.x / canvas_size.x).min(available.y / canvas_size.y);
+        let (response, painter) = ui.allocate_painter(canvas_size * scale,
+            egui::Sense::click_and_drag());
+        let origin = response.rect.min;
+        let to_minimap = |rect: egui::Rect| {
+            let rect = egui::Rect::from_min_size(origin + rect.min.to_vec2() * scale,
+                rect.size() * scale);
+            rect.union(egui::Rect::from_min_size(rect.min,
+                egui::Vec2::new(MIN_CHUNK_WIDTH_PX, rect.height())))
+        };
+        painter.rect_filled(response.rect, 0.0, COLOR_BACKGROUND);
+
+        let texture = self.memory.density_texture(ui.ctx());
+        let uv = egui::Rect::from_min_max(egui::Pos2::ZERO, egui::Pos2::new(1.0, 1.0));
+        painter.image(texture, response.rect, uv, egui::Color32::WHITE);
+        // Anomalies span the whole width so a single byte is still noticeable.
+        for anomaly in self.memory.anomalies() {
+            for rect in self.memory.canvas_rects(anomaly.ptr, anomaly.len.max(1)) {
+                let y = to_minimap(rect).center().y;
+                let stroke = egui::Stroke::new(1.0, anomaly.kind.to_color());
+                painter.hline(response.rect.x_range(), y, stroke);
+            }
+        }
+        if let Some(viewport) = self.memory.viewport() {
+            let rect = egui::Rect::from_min_max(origin + viewport.min.to_vec2() * scale,
+                origin + viewport.max.to_vec2() * scale);
+            let rect = rect.intersect(response.rect);
+            painter.rect_stroke(rect, 0.0, egui::Stroke::new(1.0, COLOR_VIEWPORT));
+        }
+
+        if response.clicked() || response.dragged() {
+            let ptr = response.interact_pointer_pos()
+                .and_then(|pos| self.memory.ptr_at(((pos - origin) / scale).to_pos2()));
+            if let Some(ptr) = ptr {
+                self.memory.center_on_ptr(ptr);
+            }
+        }
+        response
+    }
+}
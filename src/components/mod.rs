@@ -3,9 +3,27 @@ pub mod section;
 pub mod field;
 pub mod memory;
 pub mod play;
+pub mod breakpoint_form;
+pub mod usage_graph;
+pub mod bar_chart;
+pub mod filter_form;
+pub mod minimap;
+pub mod heap_bar;
+pub mod treemap;
+pub mod legend;
 
+pub use crate::components::tabbed::Tabbed;
 pub use crate::components::section::Section;
 pub use crate::components::section::SectionTitle;
 pub use crate::components::field::Field;
+pub use crate::components::field::identifier_label;
 pub use crate::components::memory::Memory;
 pub use crate::components::play::PlayToggle;
+pub use crate::components::breakpoint_form::BreakpointForm;
+pub use crate::components::usage_graph::UsageGraph;
+pub use crate::components::bar_chart::BarChart;
+pub use crate::components::filter_form::FilterForm;
+pub use crate::components::minimap::Minimap;
+pub use crate::components::heap_bar::HeapBar;
+pub use crate::components::treemap::Treemap;
+pub use crate::components::legend::Legend;
@@ -1,10 +1,11 @@
-struct Tabbed {
+/// Row of tabs, each holding the value to show while it is selected.
+pub struct Tabbed<T> {
     titles: Vec<String>,
-    contents: Vec<u8>,
+    contents: Vec<T>,
     selected_idx: usize,
 }
 
-impl Tabbed {
+impl<T: Copy> Tabbed<T> {
     pub fn new() -> Self {
         Self {
             titles: Vec::new(),
@@ -13,7 +14,7 @@ impl Tabbed {
         }
     }
 
-    pub fn add(&mut self, title: String, content: u8) {
+    pub fn add(&mut self, title: String, content: T) {
         self.titles.push(title);
         self.contents.push(content);
     }
@@ -23,4 +24,20 @@ impl Tabbed {
             self.selected_idx = idx;
         }
     }
+
+    pub fn selected(&self) -> Option<T> {
+        self.contents.get(self.selected_idx).copied()
+    }
+}
+
+impl<T> egui::Widget for &mut Tabbed<T> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        ui.horizontal(|ui| {
+            for (idx, title) in self.titles.iter().enumerate() {
+                if ui.selectable_label(idx == self.selected_idx, title).clicked() {
+                    self.selected_idx = idx;
+                }
+            }
+        }).response
+    }
 }
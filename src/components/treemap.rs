@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+
+use crate::alloc;
+use crate::components::identifier_label;
+use crate::components::memory;
+
+const COLOR_BORDER: egui::Color32 = egui::Color32::from_gray(27);
+const COLOR_LABEL: egui::Color32 = egui::Color32::BLACK;
+const LABEL_FONT_SIZE: f32 = 11.0;
+/// Tiles narrower or lower than this are not labelled.
+const MIN_LABEL_SIZE_PX: egui::Vec2 = egui::Vec2::new(60.0, 16.0);
+
+struct Group<'a> {
+    identifier: &'a str,
+    bytes: u64,
+    count: usize,
+}
+
+/// Live bytes grouped by identifier, one tile per identifier with an area proportional to its
+/// bytes.
+pub struct Treemap<'a> {
+    chunks: &'a alloc::Chunks,
+}
+
+impl<'a> Treemap<'a> {
+    pub fn new(chunks: &'a alloc::Chunks) -> Self {
+        Self {
+            chunks,
+        }
+    }
+
+    fn groups(&self) -> Vec<Group<'a>> {
+        let mut groups = HashMap::<&str, Group>::new();
+        for chunk in self.chunks.iter().map(|(_, chunk)| chunk).filter(|c| c.is_allocated()) {
+            let group = groups.entry(&chunk.identifier).or_insert(Group {
+                identifier: &chunk.identifier,
+                bytes: 0,
+                count: 0,
+            });
+            group.bytes += chunk.size;
+            group.count += 1;
+        }
+        let mut groups = groups.into_values()
+            .filter(|group| group.bytes > 0)
+            .collect::<Vec<Group>>();
+        groups.sort_unstable_by(|a, b| b.bytes.cmp(&a.bytes).then(a.identifier.cmp(b.identifier)));
+        groups
+    }
+
+    /// Shows the treemap. Returns the identifier of the tile clicked.
+    pub fn show(self, ui: &mut egui::Ui) -> Option<String> {
+        let (response, painter) = ui.allocate_painter(ui.available_size(), egui::Sense::click());
+        let groups = self.groups();
+        let areas = groups.iter().map(|group| group.bytes as f32).collect::<Vec<f32>>();
+        let tiles = squarify(&areas, response.rect);
+        let hover_pos = response.hover_pos();
+        let mut hovered = None;
+        for (group, tile) in groups.iter().zip(tiles) {
+            painter.rect_filled(tile, 0.0, memory::identifier_color(group.identifier));
+            painter.rect_stroke(tile, 0.0, egui::Stroke::new(1.0, COLOR_BORDER));
+            if tile.width() >= MIN_LABEL_SIZE_PX.x && tile.height() >= MIN_LABEL_SIZE_PX.y {
+                let label = painter.layout(
+                    format!("{}\n{} bytes", identifier_label(group.identifier), group.bytes),
+                    egui::FontId::proportional(LABEL_FONT_SIZE), COLOR_LABEL, tile.width() - 4.0);
+                painter.with_clip_rect(tile.shrink(2.0))
+                    .galley(tile.min + egui::Vec2::splat(2.0), label);
+            }
+            if hover_pos.is_some_and(|pos| tile.contains(pos)) {
+                hovered = Some(group);
+            }
+        }
+        let clicked = response.clicked();
+        if let Some(group) = hovered {
+            let text = format!("{}\n{} bytes in {} chunks",
+                identifier_label(group.identifier), group.bytes, group.count);
+            response.on_hover_text_at_pointer(text);
+        }
+        match clicked {
+            true => hovered.map(|group| group.identifier.to_string()),
+            false => None,
+        }
+    }
+}
+
+/// Worst aspect ratio of the tiles of a row laid along a side of length `side`.
+fn worst_ratio(row: &[f32], side: f32) -> f32 {
+    let sum = row.iter().sum::<f32>();
+    let max = row.iter().copied().fold(f32::MIN, f32::max);
+    let min = row.iter().copied().fold(f32::MAX, f32::min);
+    let side = side * side;
+    (side * max / (sum * sum)).max(sum * sum / (side * min))
+}
+
+/// Squarified treemap layout. `values` must be sorted from largest to smallest. Rows of tiles
+/// are laid along the shorter side of the space left, and grown while that makes their tiles
+/// squarer.
+fn squarify(values: &[f32], rect: egui::Rect) -> Vec<egui::Rect> {
+    let total = values.iter().sum::<f32>();
+    if total <= 0.0 || rect.area() <= 0.0 {
+        return Vec::new();
+    }
+    let areas = values.iter()
+        .map(|value| value / total * rect.area())
+        .collect::<Vec<f32>>();
+    let mut tiles = Vec::with_capacity(areas.len());
+    let mut rest = rect;
+    let mut start = 0;
+    while start < areas.len() {
+        let side = rest.width().min(rest.height());
+        let mut end = start + 1;
+        while end < areas.len()
+            && worst_ratio(&areas[start..=end], side) <= worst_ratio(&areas[start..end], side) {
+            end += 1;
+        }
+        let row = &areas[start..end];
+        let thickness = row.iter().sum::<f32>() / side;
+        let mut offset = 0.0;
+        for area in row {
+            let length = area / thickness;
+            let tile = match rest.width() >= rest.height() {
+                true => egui::Rect::from_min_size(rest.min + egui::Vec2::new(0.0, offset),
+                    egui::Vec2::new(thickness, length)),
+                false => egui::Rect::from_min_size(rest.min + egui::Vec2::new(offset, 0.0),
+                    egui::Vec2::new(length, thickness)),
+            };
+            tiles.push(tile);
+            offset += length;
+        }
+        match rest.width() >= rest.height() {
+            true => rest.min.x += thickness,
+            false => rest.min.y += thickness,
+        }
+        start = end;
+    }
+    tiles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{sync::mpsc, time};
+
+    #[test]
+    fn anomaly_placeholders_have_no_tile() {
+        let (tx, rx) = mpsc::channel();
+        for line in ["m:100,20,alloc_site", "c:500", "f:600,wild_free"] {
+            let timestamp = alloc::Timestamp::from_line(line, time::Duration::ZERO);
+            tx.send((alloc::Event::try_from_line(line).unwrap(), timestamp)).unwrap();
+        }
+        let mut chunks = alloc::Chunks::from_receiver(rx);
+        chunks.set_policy(alloc::AnomalyKind::Corrupted(alloc::CorruptionKind::Unknown),
+            alloc::AnomalyPolicy::Continue);
+        chunks.update();
+        let groups = Treemap::new(&chunks).groups();
+        assert_eq!(groups.len(), 1);
+        let group = &groups[0];
+        assert_eq!((group.identifier, group.bytes, group.count), ("alloc_site", 0x20, 1));
+    }
+}
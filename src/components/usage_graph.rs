@@ -0,0 +1,111 @@
+use crate::{alloc, history};
+use crate::components::memory;
+
+pub const COLOR_LIVE_BYTES: egui::Color32 = memory::COLOR_USED;
+pub const COLOR_LIVE_COUNT: egui::Color32 = egui::Color32::from_rgb(46, 204, 113);
+pub const COLOR_LARGEST_GAP: egui::Color32 = egui::Color32::LIGHT_GRAY;
+const COLOR_BACKGROUND: egui::Color32 = egui::Color32::from_gray(27);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TimeAxis {
+    Events,
+    Seconds,
+}
+
+/// Live bytes, live chunk count and largest free gap over time. Bytes and gaps share the same
+/// scale while the count has its own. Anomalies are marked by vertical lines.
+pub struct UsageGraph<'a> {
+    history: &'a history::History,
+    anomalies: &'a [alloc::Anomaly],
+    axis: TimeAxis,
+}
+
+impl<'a> UsageGraph<'a> {
+    pub fn new(history: &'a history::History, anomalies: &'a [alloc::Anomaly],
+        axis: TimeAxis) -> Self {
+        Self {
+            history,
+            anomalies,
+            axis,
+        }
+    }
+
+    fn x(&self, sample: &history::Sample) -> f32 {
+        match self.axis {
+            TimeAxis::Events => sample.event_idx as f32,
+            TimeAxis::Seconds => sample.seconds,
+        }
+    }
+
+    /// Position of an anomaly on the x axis. With wall time, the anomaly is placed on the sample
+    /// of the batch it happened in.
+    fn anomaly_x(&self, anomaly: &alloc::Anomaly) -> Option<f32> {
+        let samples = self.history.samples();
+        match self.axis {
+            TimeAxis::Events => Some(anomaly.event_idx as f32),
+            TimeAxis::Seconds => {
+                let idx = samples.partition_point(|sample| sample.event_idx < anomaly.event_idx);
+                samples.get(idx).map(|sample| sample.seconds)
+            }
+        }
+    }
+}
+
+impl egui::Widget for UsageGraph<'_> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let (mut response, painter) = ui.allocate_painter(ui.available_size(),
+            egui::Sense::hover());
+        let rect = response.rect;
+        painter.rect_filled(rect, 0.0, COLOR_BACKGROUND);
+        let samples = self.history.samples();
+        let Some(last) = samples.last() else {
+            return response;
+        };
+        let max_x = self.x(last).max(f32::EPSILON);
+        let max_bytes = samples
+            .iter()
+            .map(|sample| sample.live_bytes.max(sample.largest_gap))
+            .max()
+            .unwrap_or_default()
+            .max(1) as f32;
+        let max_count = self.history.peak_count().max(1) as f32;
+        let position = |x: f32, ratio: f32| {
+            egui::Pos2::new(rect.left() + x / max_x * rect.width(),
+                rect.bottom() - ratio * rect.height())
+        };
+
+        for anomaly in self.anomalies {
+            if let Some(x) = self.anomaly_x(anomaly) {
+                let stroke = egui::Stroke::new(1.0, anomaly.kind.to_color());
+                painter.line_segment([position(x, 0.0), position(x, 1.0)], stroke);
+            }
+        }
+
+        // There is no point in drawing more than one point per pixel.
+        let step = (samples.len() / (rect.width().max(1.0) as usize)).max(1);
+        let line = |color: egui::Color32, ratio: &dyn Fn(&history::Sample) -> f32| {
+            let points = samples
+                .iter()
+                .step_by(step)
+                .chain(std::iter::once(last))
+                .map(|sample| position(self.x(sample), ratio(sample)))
+                .collect::<Vec<egui::Pos2>>();
+            painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, color)));
+        };
+        line(COLOR_LARGEST_GAP, &|sample| sample.largest_gap as f32 / max_bytes);
+        line(COLOR_LIVE_COUNT, &|sample| sample.live_count as f32 / max_count);
+        line(COLOR_LIVE_BYTES, &|sample| sample.live_bytes as f32 / max_bytes);
+
+        if let Some(cursor) = response.hover_pos() {
+            let x = (cursor.x - rect.left()) / rect.width() * max_x;
+            let idx = samples.partition_point(|sample| self.x(sample) < x);
+            if let Some(sample) = samples.get(idx) {
+                let text = format!("event {} at {:.1}s\nlive: {} bytes in {} chunks\n\
+                    largest gap: {} bytes", sample.event_idx, sample.seconds, sample.live_bytes,
+                    sample.live_count, sample.largest_gap);
+                response = response.on_hover_text_at_pointer(text);
+            }
+        }
+        response
+    }
+}
@@ -0,0 +1,96 @@
+use std::ops;
+
+use crate::alloc::{Chunk, ChunkState};
+
+pub enum IdentifierFilter {
+    Substring(String),
+    Regex(regex::Regex),
+}
+
+impl IdentifierFilter {
+    fn matches(&self, identifier: &str) -> bool {
+        match self {
+            IdentifierFilter::Substring(substring) => identifier.contains(substring.as_str()),
+            IdentifierFilter::Regex(regex) => regex.is_match(identifier),
+        }
+    }
+}
+
+/// Chunks to highlight on the memory map. Every criterion that is set must match.
+#[derive(Default)]
+pub struct Filter {
+    pub identifier: Option<IdentifierFilter>,
+    /// Chunks starting in this range.
+    pub ptr: Option<ops::Range<u64>>,
+    pub size: Option<ops::RangeInclusive<u64>>,
+    pub state: Option<ChunkState>,
+}
+
+impl Filter {
+    pub fn is_empty(&self) -> bool {
+        self.identifier.is_none() && self.ptr.is_none() && self.size.is_none()
+            && self.state.is_none()
+    }
+
+    pub fn matches(&self, chunk: &Chunk) -> bool {
+        self.identifier.as_ref().is_none_or(|filter| filter.matches(&chunk.identifier))
+            && self.ptr.as_ref().is_none_or(|range| range.contains(&chunk.ptr))
+            && self.size.as_ref().is_none_or(|range| range.contains(&chunk.size))
+            && self.state.is_none_or(|state| state == chunk.state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(ptr: u64, size: u64, identifier: &str) -> Chunk {
+        Chunk::new(ptr, size, identifier.to_string())
+    }
+
+    #[test]
+    fn identifier_is_matched_as_a_substring_or_a_regex() {
+        let substring = IdentifierFilter::Substring(String::from("pbuf"));
+        assert!(substring.matches("lwip_pbuf_alloc"));
+        assert!(!substring.matches("lwip_mem_malloc"));
+        let literal = IdentifierFilter::Substring(String::from("^lwip"));
+        assert!(!literal.matches("lwip_pbuf_alloc"));
+        let regex = IdentifierFilter::Regex(regex::Regex::new("^lwip_(pbuf|mem)_").unwrap());
+        assert!(regex.matches("lwip_pbuf_alloc"));
+        assert!(regex.matches("lwip_mem_malloc"));
+        assert!(!regex.matches("tcp_lwip_pbuf_alloc"));
+    }
+
+    #[test]
+    fn ptr_range_is_exclusive_and_size_range_inclusive() {
+        let filter = Filter {
+            ptr: Some(0x100..0x200),
+            size: Some(0x10..=0x20),
+            ..Default::default()
+        };
+        assert!(filter.matches(&chunk(0x100, 0x10, "site")));
+        assert!(filter.matches(&chunk(0x1ff, 0x20, "site")));
+        assert!(!filter.matches(&chunk(0x200, 0x10, "site")));
+        assert!(!filter.matches(&chunk(0xff, 0x10, "site")));
+        assert!(!filter.matches(&chunk(0x100, 0xf, "site")));
+        assert!(!filter.matches(&chunk(0x100, 0x21, "site")));
+    }
+
+    #[test]
+    fn every_criterion_set_must_match() {
+        let filter = Filter {
+            identifier: Some(IdentifierFilter::Substring(String::from("pbuf"))),
+            state: Some(ChunkState::Corrupted),
+            ..Default::default()
+        };
+        let mut corrupted = chunk(0x100, 0x10, "pbuf_alloc");
+        corrupted.set_state(ChunkState::Corrupted);
+        assert!(filter.matches(&corrupted));
+        assert!(!filter.matches(&chunk(0x100, 0x10, "pbuf_alloc")));
+        corrupted.identifier = String::from("mem_malloc");
+        assert!(!filter.matches(&corrupted));
+        assert!(Filter::default().is_empty());
+        assert!(!filter.is_empty());
+        assert!(Filter::default().matches(&corrupted));
+    }
+}
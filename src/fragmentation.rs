@@ -0,0 +1,104 @@
+use std::ops;
+
+/// Free space of the heap region, computed from the gaps between live chunks.
+#[derive(Clone, Default)]
+pub struct Fragmentation {
+    /// Free ranges, sorted by address.
+    pub gaps: Vec<ops::Range<u64>>,
+    pub free_bytes: u64,
+    pub fragment_count: usize,
+    pub largest: Option<ops::Range<u64>>,
+}
+
+impl Fragmentation {
+    /// Computes the gaps of `heap` not covered by `ranges`, which must be sorted by start.
+    pub fn new(heap: &ops::Range<u64>, ranges: &[ops::Range<u64>]) -> Self {
+        let mut fragmentation = Self::default();
+        let mut end = heap.start;
+        let clamp = |ptr: u64| ptr.clamp(heap.start, heap.end);
+        let ranges = ranges
+            .iter()
+            .map(|range| clamp(range.start)..clamp(range.end))
+            .chain(std::iter::once(heap.end..heap.end));
+        for range in ranges {
+            if range.start > end {
+                fragmentation.add_gap(end..range.start);
+            }
+            end = end.max(range.end);
+        }
+        fragmentation
+    }
+
+    /// Same as `new` when the gaps are already known.
+    pub fn from_gaps(gaps: impl Iterator<Item = ops::Range<u64>>) -> Self {
+        let mut fragmentation = Self::default();
+        gaps.for_each(|gap| fragmentation.add_gap(gap));
+        fragmentation
+    }
+
+    fn add_gap(&mut self, gap: ops::Range<u64>) {
+        let size = gap.end - gap.start;
+        self.free_bytes += size;
+        self.fragment_count += 1;
+        if size > self.largest_size() {
+            self.largest = Some(gap.clone());
+        }
+        self.gaps.push(gap);
+    }
+
+    pub fn largest_size(&self) -> u64 {
+        self.largest.as_ref().map_or(0, |gap| gap.end - gap.start)
+    }
+
+    /// External fragmentation, from 0 when all the free space is in one block to almost 1 when
+    /// it is scattered in many small blocks.
+    pub fn ratio(&self) -> f32 {
+        match self.free_bytes {
+            0 => 0.0,
+            free_bytes => 1.0 - self.largest_size() as f32 / free_bytes as f32,
+        }
+    }
+
+    /// Whether an allocation of `size` bytes can fit in one of the gaps.
+    pub fn fits(&self, size: u64) -> bool {
+        size <= self.largest_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_heap_has_no_fragmentation() {
+        let heap = 0x100..0x200;
+        let fragmentation = Fragmentation::new(&heap, &[0x100..0x180, 0x180..0x200]);
+        assert!(fragmentation.gaps.is_empty());
+        assert_eq!(fragmentation.free_bytes, 0);
+        assert_eq!(fragmentation.ratio(), 0.0);
+        assert!(!fragmentation.fits(1));
+    }
+
+    #[test]
+    fn chunks_outside_the_region_are_cut_to_it() {
+        let heap = 0x100..0x200;
+        let fragmentation = Fragmentation::new(&heap, &[0x80..0x120, 0x1f0..0x280, 0x300..0x310]);
+        let gap = 0x120..0x1f0;
+        assert!(fragmentation.gaps == [gap.clone()]);
+        assert_eq!(fragmentation.free_bytes, 0xd0);
+        assert!(fragmentation.largest == Some(gap));
+    }
+
+    #[test]
+    fn adjacent_and_overlapping_chunks_leave_no_gap_between_them() {
+        let heap = 0..0x100;
+        let fragmentation = Fragmentation::new(&heap,
+            &[0x10..0x20, 0x20..0x30, 0x28..0x40, 0x30..0x38, 0x80..0x90]);
+        assert!(fragmentation.gaps == [0..0x10, 0x40..0x80, 0x90..0x100]);
+        assert_eq!(fragmentation.fragment_count, 3);
+        assert_eq!(fragmentation.largest_size(), 0x70);
+        assert!(fragmentation.fits(0x70));
+        assert!(!fragmentation.fits(0x71));
+        assert_eq!(fragmentation.ratio(), 1.0 - 0x70 as f32 / 0xc0 as f32);
+    }
+}
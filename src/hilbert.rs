@@ -0,0 +1,156 @@
+use std::{collections::HashMap, ops};
+
+/// Square of `side` cells whose top left cell is at `x`, `y`.
+#[derive(Clone, Copy)]
+pub struct Square {
+    pub x: u64,
+    pub y: u64,
+    pub side: u64,
+}
+
+/// Side of a cell edge, in cells, starting at `x`, `y`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Edge {
+    pub x: u64,
+    pub y: u64,
+    pub is_horizontal: bool,
+}
+
+fn rotate(side: u64, x: &mut u64, y: &mut u64, rx: u64, ry: u64) {
+    if ry == 0 {
+        if rx == 1 {
+            *x = side - 1 - *x;
+            *y = side - 1 - *y;
+        }
+        std::mem::swap(x, y);
+    }
+}
+
+/// Cell of the `d`th address along a Hilbert curve filling a square of `side` cells. `side` must
+/// be a power of two.
+pub fn d2xy(side: u64, d: u64) -> (u64, u64) {
+    let (mut x, mut y) = (0, 0);
+    let mut t = d;
+    let mut s = 1;
+    while s < side {
+        let rx = 1 & (t / 2);
+        let ry = 1 & (t ^ rx);
+        rotate(s, &mut x, &mut y, rx, ry);
+        x += s * rx;
+        y += s * ry;
+        t /= 4;
+        s *= 2;
+    }
+    (x, y)
+}
+
+/// Address of the cell at `x`, `y` along a Hilbert curve filling a square of `side` cells.
+pub fn xy2d(side: u64, mut x: u64, mut y: u64) -> u64 {
+    let mut d = 0;
+    let mut s = side / 2;
+    while s > 0 {
+        let rx = (x & s > 0) as u64;
+        let ry = (y & s > 0) as u64;
+        d += s * s * ((3 * rx) ^ ry);
+        rotate(side, &mut x, &mut y, rx, ry);
+        s /= 2;
+    }
+    d
+}
+
+/// Squares covering `range`. An aligned run of 4^k addresses fills a square of 2^k cells, so a
+/// range is split into the largest such runs.
+pub fn squares(side: u64, range: ops::Range<u64>) -> Vec<Square> {
+    let mut squares = Vec::new();
+    let mut start = range.start;
+    while start < range.end {
+        let mut len = 1;
+        while start.is_multiple_of(len * 4) && start + len * 4 <= range.end {
+            len *= 4;
+        }
+        let square_side = len.isqrt();
+        let (x, y) = d2xy(side, start);
+        squares.push(Square {
+            x: x - x % square_side,
+            y: y - y % square_side,
+            side: square_side,
+        });
+        start += len;
+    }
+    squares
+}
+
+/// Cell edges on the border of the union of `squares`. Edges shared by two squares are inside
+/// the union and left out.
+pub fn outline(squares: &[Square]) -> Vec<Edge> {
+    let mut counts = HashMap::<Edge, usize>::new();
+    for square in squares {
+        for i in 0..square.side {
+            let edges = [
+                Edge { x: square.x + i, y: square.y, is_horizontal: true },
+                Edge { x: square.x + i, y: square.y + square.side, is_horizontal: true },
+                Edge { x: square.x, y: square.y + i, is_horizontal: false },
+                Edge { x: square.x + square.side, y: square.y + i, is_horizontal: false },
+            ];
+            for edge in edges {
+                *counts.entry(edge).or_default() += 1;
+            }
+        }
+    }
+    counts.into_iter()
+        .filter(|&(_, count)| count == 1)
+        .map(|(edge, _)| edge)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    const SIDE: u64 = 512;
+
+    #[test]
+    fn xy2d_inverts_d2xy() {
+        for d in 0..SIDE * SIDE {
+            let (x, y) = d2xy(SIDE, d);
+            assert!(x < SIDE && y < SIDE);
+            assert_eq!(xy2d(SIDE, x, y), d);
+        }
+    }
+
+    #[test]
+    fn consecutive_addresses_are_neighbour_cells() {
+        for d in 1..SIDE * SIDE {
+            let (x0, y0) = d2xy(SIDE, d - 1);
+            let (x1, y1) = d2xy(SIDE, d);
+            assert_eq!(x0.abs_diff(x1) + y0.abs_diff(y1), 1);
+        }
+    }
+
+    #[test]
+    fn squares_cover_exactly_the_range() {
+        for range in [0..1, 3..4, 0..16, 5..300, 17..4096, 1000..70000, 0..SIDE * SIDE] {
+            let mut cells = HashSet::new();
+            for square in squares(SIDE, range.clone()) {
+                for x in square.x..square.x + square.side {
+                    for y in square.y..square.y + square.side {
+                        assert!(cells.insert((x, y)));
+                    }
+                }
+            }
+            let expected = range.map(|d| d2xy(SIDE, d)).collect::<HashSet<(u64, u64)>>();
+            assert!(cells == expected);
+        }
+    }
+
+    #[test]
+    fn outline_leaves_out_shared_edges() {
+        let square = Square { x: 4, y: 4, side: 2 };
+        assert_eq!(outline(&[square]).len(), 8);
+        let neighbour = Square { x: 6, y: 4, side: 2 };
+        let edges = outline(&[square, neighbour]);
+        assert_eq!(edges.len(), 12);
+        assert!(!edges.contains(&Edge { x: 6, y: 4, is_horizontal: false }));
+    }
+}
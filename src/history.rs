@@ -0,0 +1,96 @@
+use std::time;
+
+/// Samples kept. Once there are this many, every two samples are merged into one.
+const MAX_SAMPLES: usize = 1 << 16;
+
+/// Totals of the heap after a batch of events was applied.
+#[derive(Clone, Copy)]
+pub struct Sample {
+    /// Number of events applied so far.
+    pub event_idx: usize,
+    /// Seconds since the tracking started, when the last event of the batch was read.
+    pub seconds: f32,
+    pub live_bytes: u64,
+    pub live_count: usize,
+    pub largest_gap: u64,
+}
+
+pub struct History {
+    samples: Vec<Sample>,
+    peak_bytes: u64,
+    peak_count: usize,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self {
+            samples: Vec::new(),
+            peak_bytes: 0,
+            peak_count: 0,
+        }
+    }
+
+    /// Keeps track of the peaks, which can happen in the middle of a batch.
+    pub fn observe(&mut self, live_bytes: u64, live_count: usize) {
+        self.peak_bytes = self.peak_bytes.max(live_bytes);
+        self.peak_count = self.peak_count.max(live_count);
+    }
+
+    /// `host` is the host timestamp of the last event applied, so the events kept while the view
+    /// was paused are not plotted at the time it was resumed.
+    pub fn push(&mut self, event_idx: usize, host: time::Duration, live_bytes: u64,
+        live_count: usize, largest_gap: u64) {
+        if self.samples.len() == MAX_SAMPLES {
+            self.halve();
+        }
+        self.samples.push(Sample {
+            event_idx,
+            seconds: host.as_secs_f32(),
+            live_bytes,
+            live_count,
+            largest_gap,
+        });
+    }
+
+    /// Merges every two samples into the one with the most live bytes, so the spikes stay on the
+    /// graph. The older samples get coarser the longer the tracking goes.
+    fn halve(&mut self) {
+        self.samples = self.samples
+            .chunks(2)
+            .filter_map(|pair| pair.iter().max_by_key(|sample| sample.live_bytes).copied())
+            .collect();
+    }
+
+    pub fn samples(&self) -> &[Sample] {
+        &self.samples
+    }
+
+    pub fn peak_bytes(&self) -> u64 {
+        self.peak_bytes
+    }
+
+    pub fn peak_count(&self) -> usize {
+        self.peak_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_are_halved_keeping_spikes_and_the_last_one() {
+        let mut history = History::new();
+        for idx in 0..=MAX_SAMPLES {
+            let live_bytes = match idx == 11 {
+                true => 0x1000,
+                false => 0x10,
+            };
+            history.push(idx, time::Duration::ZERO, live_bytes, 1, 0);
+        }
+        let samples = history.samples();
+        assert_eq!(samples.len(), MAX_SAMPLES / 2 + 1);
+        assert!(samples.iter().any(|sample| sample.live_bytes == 0x1000));
+        assert_eq!(samples.last().map(|sample| sample.event_idx), Some(MAX_SAMPLES));
+    }
+}
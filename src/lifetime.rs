@@ -0,0 +1,147 @@
+use std::{collections::HashMap, time};
+
+use crate::alloc::Chunk;
+
+/// Lifetimes up to this many events are counted exactly to detect short-lived allocations.
+pub const MAX_SHORT_LIVED_EVENTS: usize = 16;
+const BUCKET_COUNT: usize = 24;
+
+/// Lifetimes, in events, of the chunks allocated by one identifier.
+pub struct Histogram {
+    /// Bucket `i` counts the lifetimes from `2^i` to `2^(i + 1) - 1` events. The last one also
+    /// counts everything above.
+    buckets: [usize; BUCKET_COUNT],
+    /// Index `i` counts the lifetimes of exactly `i` events.
+    short: [usize; MAX_SHORT_LIVED_EVENTS + 1],
+    count: usize,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            buckets: [0; BUCKET_COUNT],
+            short: [0; MAX_SHORT_LIVED_EVENTS + 1],
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, events: usize) {
+        let bucket = (usize::BITS - 1 - events.max(1).leading_zeros()) as usize;
+        self.buckets[bucket.min(BUCKET_COUNT - 1)] += 1;
+        if let Some(short) = self.short.get_mut(events) {
+            *short += 1;
+        }
+        self.count += 1;
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Number of chunks freed at most `events` events after being allocated.
+    pub fn short_lived(&self, events: usize) -> usize {
+        self.short.iter().take(events.min(MAX_SHORT_LIVED_EVENTS) + 1).sum()
+    }
+
+    /// Buckets up to the last non-empty one, with the lowest lifetime of each.
+    pub fn buckets(&self) -> Vec<(usize, usize)> {
+        let len = self.buckets.iter().rposition(|&count| count > 0).map_or(0, |idx| idx + 1);
+        self.buckets[..len]
+            .iter()
+            .enumerate()
+            .map(|(idx, &count)| (1 << idx, count))
+            .collect()
+    }
+}
+
+/// How long the freed chunks lived, in host time and, when the target sends timestamps, in target
+/// ticks. Lifetimes in events are also kept per identifier.
+#[derive(Default)]
+pub struct Lifetimes {
+    count: usize,
+    total: time::Duration,
+    longest: time::Duration,
+    ticks_count: usize,
+    total_ticks: u64,
+    by_identifier: HashMap<String, Histogram>,
+}
+
+impl Lifetimes {
+    pub fn observe(&mut self, chunk: &Chunk) {
+        if let Some(freed_event) = chunk.freed_event {
+            self.by_identifier
+                .entry(chunk.identifier.clone())
+                .or_insert_with(Histogram::new)
+                .observe(freed_event - chunk.allocated_event);
+        }
+        let (Some(allocated_at), Some(freed_at)) = (chunk.allocated_at, chunk.freed_at) else {
+            return;
+        };
+        let lifetime = freed_at.host.saturating_sub(allocated_at.host);
+        self.count += 1;
+        self.total += lifetime;
+        self.longest = self.longest.max(lifetime);
+        if let (Some(allocated), Some(freed)) = (allocated_at.target, freed_at.target) {
+            self.ticks_count += 1;
+            self.total_ticks += freed.wrapping_sub(allocated);
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn by_identifier(&self) -> &HashMap<String, Histogram> {
+        &self.by_identifier
+    }
+
+    pub fn mean(&self) -> Option<time::Duration> {
+        match self.count {
+            0 => None,
+            count => Some(self.total / count as u32),
+        }
+    }
+
+    pub fn longest(&self) -> time::Duration {
+        self.longest
+    }
+
+    pub fn mean_ticks(&self) -> Option<u64> {
+        self.total_ticks.checked_div(self.ticks_count as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn histogram(lifetimes: &[usize]) -> Histogram {
+        let mut histogram = Histogram::new();
+        lifetimes.iter().for_each(|&events| histogram.observe(events));
+        histogram
+    }
+
+    #[test]
+    fn lifetimes_fall_in_power_of_two_buckets() {
+        let histogram = histogram(&[0, 1, 2, 3, 4, 7, 8, 1 << 30]);
+        let buckets = histogram.buckets();
+        assert_eq!(buckets.len(), BUCKET_COUNT);
+        assert_eq!(&buckets[..4], [(1, 2), (2, 2), (4, 2), (8, 1)]);
+        assert_eq!(buckets[BUCKET_COUNT - 1], (1 << (BUCKET_COUNT - 1), 1));
+        assert_eq!(histogram.count(), 8);
+        assert!(Histogram::new().buckets().is_empty());
+    }
+
+    #[test]
+    fn short_lived_counts_lifetimes_up_to_the_threshold() {
+        let histogram = histogram(&[1, 2, 2, 5, MAX_SHORT_LIVED_EVENTS,
+            MAX_SHORT_LIVED_EVENTS + 1]);
+        assert_eq!(histogram.short_lived(0), 0);
+        assert_eq!(histogram.short_lived(1), 1);
+        assert_eq!(histogram.short_lived(2), 3);
+        assert_eq!(histogram.short_lived(4), 3);
+        assert_eq!(histogram.short_lived(MAX_SHORT_LIVED_EVENTS), 5);
+        // Longer lifetimes are only counted in the buckets.
+        assert_eq!(histogram.short_lived(usize::MAX), 5);
+    }
+}
@@ -1,11 +1,30 @@
 mod components;
 mod debug_panel;
 mod alloc;
+mod breakpoint;
+mod history;
+mod fragmentation;
+mod simulate;
+mod lifetime;
+mod sizes;
+mod filter;
+mod hilbert;
 
-use std::process;
+use std::{collections::BTreeMap, process, sync::mpsc};
 use eframe::egui;
 
+use components::identifier_label;
+
 const SIDE_PANEL_WIDTH: f32 = 300.0;
+const ANOMALY_LIST_HEIGHT: f32 = 200.0;
+const USAGE_PANEL_HEIGHT: f32 = 160.0;
+const MINIMAP_WIDTH: f32 = 80.0;
+const DEFAULT_FIT_SIZE: u64 = 4 * 1024;
+const DEFAULT_SHORT_LIVED_EVENTS: usize = 4;
+const SHORT_LIVED_LIST_LEN: usize = 10;
+const REPORT_PATH: &str = "heapvue-pools.txt";
+const DEFAULT_QUARANTINE_CHUNKS: usize = 1024;
+const DEFAULT_QUARANTINE_BYTES: u64 = 64 * 1024;
 
 fn main() -> Result<(), eframe::Error> {
     let options = eframe::NativeOptions {
@@ -20,49 +39,709 @@ fn main() -> Result<(), eframe::Error> {
     )
 }
 
+/// Views of the heap in the central panel.
+#[derive(Clone, Copy, PartialEq)]
+enum View {
+    Map,
+    Bar,
+    Treemap,
+}
+
 struct App {
     memory: components::Memory,
+    /// Why the tracked process could not be started.
+    process_error: Option<String>,
+    selected_anomaly: Option<usize>,
+    breakpoint_form: components::BreakpointForm,
+    time_axis: components::usage_graph::TimeAxis,
+    heap_start: String,
+    heap_end: String,
+    fit_size: u64,
+    strategy: simulate::Strategy,
+    lifetime_identifier: Option<String>,
+    short_lived_events: usize,
+    size_identifier: Option<String>,
+    report_status: String,
+    filter_form: components::FilterForm,
+    go_to_address: String,
+    views: components::Tabbed<View>,
+    heap_bar: components::HeapBar,
+    selection_status: String,
 }
 
 impl App {
     pub fn new(creation_context: &eframe::CreationContext) -> Self {
         let mut command = process::Command::new("python");
         command.arg("./test.py");
-        let chunks = alloc::Chunks::new(command, creation_context.egui_ctx.clone());
+        // Without the tracked process the viewer still opens, with nothing to show but the error.
+        let (chunks, process_error) = match alloc::Chunks::new(command,
+            creation_context.egui_ctx.clone()) {
+            Ok(chunks) => (chunks, None),
+            Err(error) => {
+                let (_, rx) = mpsc::channel();
+                let error = format!("Could not start python ./test.py: {}", error);
+                (alloc::Chunks::from_receiver(rx), Some(error))
+            }
+        };
+        let mut views = components::Tabbed::new();
+        views.add(String::from("Map"), View::Map);
+        views.add(String::from("Bar"), View::Bar);
+        views.add(String::from("Treemap"), View::Treemap);
         Self {
             memory: components::Memory::new(chunks),
+            process_error,
+            selected_anomaly: None,
+            breakpoint_form: components::BreakpointForm::new(),
+            time_axis: components::usage_graph::TimeAxis::Events,
+            heap_start: String::new(),
+            heap_end: String::new(),
+            fit_size: DEFAULT_FIT_SIZE,
+            strategy: simulate::Strategy::FirstFit,
+            lifetime_identifier: None,
+            short_lived_events: DEFAULT_SHORT_LIVED_EVENTS,
+            size_identifier: None,
+            report_status: String::new(),
+            filter_form: components::FilterForm::new(),
+            go_to_address: String::new(),
+            views,
+            heap_bar: components::HeapBar::new(),
+            selection_status: String::new(),
+        }
+    }
+
+    fn side_panel(&mut self, ui: &mut egui::Ui) {
+        ui.add_space(16.0);
+        ui.add(components::SectionTitle(String::from("Controls")));
+        if let Some(error) = &self.process_error {
+            ui.label(egui::RichText::new(error).color(egui::Color32::RED));
+        }
+        let play_pause_button_label = match self.memory.do_advance() {
+            true => "Pause",
+            false => "Play",
+        };
+        if ui.button(play_pause_button_label).clicked() {
+            self.memory.set_do_advance(!self.memory.do_advance());
+        }
+        let mut color_by = self.memory.color_by();
+        ui.horizontal(|ui| {
+            ui.label("Colour by");
+            egui::ComboBox::from_id_source("color_by")
+                .selected_text(color_by.to_string())
+                .show_ui(ui, |ui| {
+                    for option in components::memory::ColorBy::ALL {
+                        ui.selectable_value(&mut color_by, option, option.to_string());
+                    }
+                });
+        });
+        if color_by != self.memory.color_by() {
+            self.memory.set_color_by(color_by);
+        }
+        let mut layout = self.memory.layout();
+        ui.horizontal(|ui| {
+            ui.label("Layout");
+            for option in components::memory::Layout::ALL {
+                ui.selectable_value(&mut layout, option, option.to_string());
+            }
+        });
+        let mut bytes_per_line = self.memory.bytes_per_line();
+        let mut byte_width_px = self.memory.byte_width_px();
+        ui.horizontal(|ui| {
+            ui.label("Row");
+            egui::ComboBox::from_id_source("bytes_per_line")
+                .selected_text(format!("{} bytes", bytes_per_line))
+                .show_ui(ui, |ui| {
+                    for option in components::memory::BYTES_PER_LINE_OPTIONS {
+                        ui.selectable_value(&mut bytes_per_line, option,
+                            format!("{} bytes", option));
+                    }
+                });
+            ui.add(egui::DragValue::new(&mut byte_width_px)
+                .clamp_range(components::memory::BYTE_WIDTH_PX_RANGE)
+                .suffix(" px per byte"));
+        });
+        if layout != self.memory.layout() || bytes_per_line != self.memory.bytes_per_line()
+            || byte_width_px != self.memory.byte_width_px() {
+            self.memory.set_layout(layout, bytes_per_line, byte_width_px);
+        }
+        let mut gridlines = self.memory.gridlines();
+        ui.horizontal(|ui| {
+            ui.label("Gridlines");
+            egui::ComboBox::from_id_source("gridlines")
+                .selected_text(gridlines.to_string())
+                .show_ui(ui, |ui| {
+                    for option in components::memory::Gridlines::ALL {
+                        ui.selectable_value(&mut gridlines, option, option.to_string());
+                    }
+                });
+        });
+        if gridlines != self.memory.gridlines() {
+            self.memory.set_gridlines(gridlines);
+        }
+        let mut limit = self.memory.quarantine_limit();
+        ui.horizontal(|ui| {
+            ui.label("Quarantine");
+            match &mut limit {
+                alloc::QuarantineLimit::Chunks(count) => {
+                    ui.add(egui::DragValue::new(count).suffix(" chunks"));
+                }
+                alloc::QuarantineLimit::Bytes(bytes) => {
+                    ui.add(egui::DragValue::new(bytes).suffix(" bytes"));
+                }
+            }
+            if ui.button("Chunks").clicked() {
+                limit = alloc::QuarantineLimit::Chunks(DEFAULT_QUARANTINE_CHUNKS);
+            }
+            if ui.button("Bytes").clicked() {
+                limit = alloc::QuarantineLimit::Bytes(DEFAULT_QUARANTINE_BYTES);
+            }
+        });
+        if limit != self.memory.quarantine_limit() {
+            self.memory.set_quarantine_limit(limit);
+        }
+        let mut go_to = None;
+        ui.horizontal(|ui| {
+            ui.label("Go to");
+            let input = ui.add(egui::TextEdit::singleline(&mut self.go_to_address)
+                .hint_text("0x3a400")
+                .desired_width(80.0));
+            let submitted = input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if let Some(ptr) = alloc::parse_hex(&self.go_to_address) {
+                if ui.button("Go").clicked() || submitted {
+                    go_to = Some(ptr);
+                }
+            }
+        });
+        if let Some(ptr) = go_to {
+            self.memory.go_to(ptr);
+        }
+        if let Some(breakpoint) = self.memory.fired_breakpoint() {
+            let label = format!("Stopped on {}", breakpoint.condition);
+            ui.label(egui::RichText::new(label).color(egui::Color32::YELLOW));
+        }
+        ui.add_space(16.0);
+        ui.add(components::SectionTitle(String::from("Legend")));
+        ui.add(components::Legend::new(&self.memory));
+        ui.add_space(16.0);
+        ui.add(components::SectionTitle(String::from("Fragmentation")));
+        let chunks = self.memory.chunks();
+        let fragmentation = chunks.fragmentation();
+        let heap = format!("{:#01x}..{:#01x}", chunks.heap().start, chunks.heap().end);
+        let free_bytes = format!("{} bytes", fragmentation.free_bytes);
+        let largest = format!("{} bytes", fragmentation.largest_size());
+        let fragments = fragmentation.fragment_count.to_string();
+        let ratio = format!("{:.1} %", fragmentation.ratio() * 100.0);
+        let fits = match fragmentation.fits(self.fit_size) {
+            true => "fits",
+            false => "does not fit",
+        };
+        ui.add(components::Field::new("Heap", &heap));
+        ui.add(components::Field::new("Free", &free_bytes));
+        ui.add(components::Field::new("Largest block", &largest));
+        ui.add(components::Field::new("Fragments", &fragments));
+        ui.add(components::Field::new("Fragmentation", &ratio));
+        ui.horizontal(|ui| {
+            ui.label("Next allocation of");
+            ui.add(egui::DragValue::new(&mut self.fit_size).suffix(" bytes"));
+            ui.label(fits);
+        });
+        let mut heap = None;
+        ui.horizontal(|ui| {
+            ui.label("Heap");
+            ui.add(egui::TextEdit::singleline(&mut self.heap_start)
+                .hint_text("0x0")
+                .desired_width(80.0));
+            ui.label("..");
+            ui.add(egui::TextEdit::singleline(&mut self.heap_end)
+                .hint_text("0x40000")
+                .desired_width(80.0));
+            let start = alloc::parse_hex(&self.heap_start);
+            let end = alloc::parse_hex(&self.heap_end);
+            if let (Some(start), Some(end)) = (start, end) {
+                if start < end && ui.button("Set").clicked() {
+                    heap = Some(start..end);
+                }
+            }
+        });
+        if let Some(heap) = heap {
+            self.memory.set_heap(heap);
+        }
+        let lifetimes = self.memory.chunks().lifetimes();
+        if let Some(mean) = lifetimes.mean() {
+            ui.add_space(16.0);
+            ui.add(components::SectionTitle(String::from("Lifetimes")));
+            let count = lifetimes.count().to_string();
+            let mean = format!("{:.3} s", mean.as_secs_f32());
+            let longest = format!("{:.3} s", lifetimes.longest().as_secs_f32());
+            ui.add(components::Field::new("Freed chunks", &count));
+            ui.add(components::Field::new("Mean", &mean));
+            ui.add(components::Field::new("Longest", &longest));
+            if let Some(mean_ticks) = lifetimes.mean_ticks() {
+                ui.add(components::Field::new("Mean ticks", &mean_ticks.to_string()));
+            }
+            let mut identifiers = lifetimes.by_identifier()
+                .iter()
+                .collect::<Vec<(&String, &lifetime::Histogram)>>();
+            identifiers.sort_unstable_by(|a, b| b.1.count().cmp(&a.1.count()).then(a.0.cmp(b.0)));
+            let selected = self.lifetime_identifier.clone()
+                .or_else(|| identifiers.first().map(|(identifier, _)| (*identifier).clone()));
+            egui::ComboBox::from_id_source("lifetime_identifier")
+                .selected_text(selected.as_deref().map_or("", identifier_label))
+                .show_ui(ui, |ui| {
+                    for (identifier, _) in identifiers.iter() {
+                        let value = Some((*identifier).clone());
+                        ui.selectable_value(&mut self.lifetime_identifier, value,
+                            identifier_label(identifier));
+                    }
+                });
+            if let Some(histogram) = selected.and_then(|id| lifetimes.by_identifier().get(&id)) {
+                let bars = histogram.buckets()
+                    .into_iter()
+                    .map(|(events, count)| (format!("{}+ events", events), count))
+                    .collect::<Vec<(String, usize)>>();
+                ui.add(components::BarChart::new(&bars, components::memory::COLOR_USED));
+            }
+            ui.horizontal(|ui| {
+                ui.label("Short-lived within");
+                ui.add(egui::DragValue::new(&mut self.short_lived_events)
+                    .clamp_range(1..=lifetime::MAX_SHORT_LIVED_EVENTS)
+                    .suffix(" events"));
+            });
+            let mut short_lived = identifiers
+                .iter()
+                .map(|(identifier, histogram)| {
+                    (*identifier, histogram.short_lived(self.short_lived_events), histogram.count())
+                })
+                .filter(|&(_, short_lived, _)| short_lived > 0)
+                .collect::<Vec<(&String, usize, usize)>>();
+            short_lived.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+            for (identifier, short_lived, count) in short_lived.iter().take(SHORT_LIVED_LIST_LEN) {
+                let value = format!("{} of {}", short_lived, count);
+                ui.add(components::Field::new(identifier_label(identifier), &value));
+            }
+        }
+        let sizes = self.memory.chunks().sizes();
+        if !sizes.classes().is_empty() {
+            ui.add_space(16.0);
+            ui.add(components::SectionTitle(String::from("Sizes")));
+            let mut identifiers = sizes.by_identifier().keys().collect::<Vec<&String>>();
+            identifiers.sort_unstable();
+            egui::ComboBox::from_id_source("size_identifier")
+                .selected_text(self.size_identifier.as_deref().map_or("all", identifier_label))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.size_identifier, None, "all");
+                    for identifier in identifiers {
+                        ui.selectable_value(&mut self.size_identifier, Some(identifier.clone()),
+                            identifier_label(identifier));
+                    }
+                });
+            let bars = match self.size_identifier.as_ref() {
+                Some(identifier) => sizes.by_identifier()
+                    .get(identifier)
+                    .into_iter()
+                    .flatten()
+                    .map(|(size, &count)| (format!("{}B", size), count))
+                    .collect::<Vec<(String, usize)>>(),
+                None => sizes.classes()
+                    .iter()
+                    .map(|(size, class)| (format!("{}B, peak {}", size, class.peak), class.count))
+                    .collect::<Vec<(String, usize)>>(),
+            };
+            ui.add(components::BarChart::new(&bars, components::memory::COLOR_USED));
+            ui.label(format!("Pools: {}", sizes.proposal_text()));
+            ui.horizontal(|ui| {
+                if ui.button("Copy report").clicked() {
+                    let report = sizes.report();
+                    ui.output_mut(|output| output.copied_text = report);
+                    self.report_status = String::from("Copied");
+                }
+                if ui.button("Save report").clicked() {
+                    self.report_status = match std::fs::write(REPORT_PATH, sizes.report()) {
+                        Ok(()) => format!("Saved to {}", REPORT_PATH),
+                        Err(error) => format!("Could not save: {}", error),
+                    };
+                }
+                ui.label(&self.report_status);
+            });
+        }
+        if !self.memory.chunks().thread_live_bytes().is_empty() {
+            ui.add_space(16.0);
+            ui.add(components::SectionTitle(String::from("Threads")));
+            let mut threads = self.memory.chunks()
+                .thread_live_bytes()
+                .iter()
+                .map(|(&thread, &bytes)| (thread, bytes))
+                .collect::<Vec<(u64, u64)>>();
+            threads.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            for (thread, bytes) in threads {
+                let title = egui::RichText::new(format!("thread {:x}", thread))
+                    .color(components::memory::thread_color(thread));
+                ui.horizontal(|ui| {
+                    ui.label(title);
+                    ui.label(format!("{} bytes", bytes));
+                });
+            }
+        }
+        ui.add_space(16.0);
+        ui.add(components::SectionTitle(String::from("Simulation")));
+        egui::ComboBox::from_id_source("strategy")
+            .selected_text(self.strategy.to_string())
+            .show_ui(ui, |ui| {
+                for strategy in simulate::Strategy::ALL {
+                    ui.selectable_value(&mut self.strategy, strategy, strategy.to_string());
+                }
+            });
+        let chunks = self.memory.chunks();
+        let prediction = simulate::place(self.strategy, &chunks.fragmentation().gaps,
+            self.fit_size);
+        let placed_at = prediction.map_or(String::from("nowhere"), |ptr| format!("{:#01x}", ptr));
+        let label = format!("{} bytes would be placed at", self.fit_size);
+        ui.add(components::Field::new(&label, &placed_at));
+        self.memory.set_prediction(prediction.map(|ptr| ptr..ptr + self.fit_size));
+        ui.horizontal(|ui| {
+            if ui.button("Replay trace").clicked() {
+                let chunks = self.memory.chunks();
+                let simulation = simulate::Simulation::replay(self.strategy,
+                    chunks.heap().clone(), chunks.trace());
+                self.memory.set_simulation(Some(simulation));
+            }
+            if self.memory.simulation().is_some() && ui.button("Clear").clicked() {
+                self.memory.set_simulation(None);
+            }
+        });
+        if let Some(simulation) = self.memory.simulation() {
+            let chunks = self.memory.chunks();
+            let real = chunks.fragmentation();
+            let simulated = simulation.fragmentation();
+            let compare = |real: String, simulated: String| format!("{} / {}", real, simulated);
+            let strategy = format!("real / {}", simulation.strategy);
+            ui.add(components::Field::new("", &strategy));
+            ui.add(components::Field::new("Footprint", &compare(
+                format!("{} bytes", chunks.footprint()),
+                format!("{} bytes", simulation.footprint()))));
+            ui.add(components::Field::new("Largest block", &compare(
+                format!("{} bytes", real.largest_size()),
+                format!("{} bytes", simulated.largest_size()))));
+            ui.add(components::Field::new("Fragments", &compare(
+                real.fragment_count.to_string(),
+                simulated.fragment_count.to_string())));
+            ui.add(components::Field::new("Fragmentation", &compare(
+                format!("{:.1} %", real.ratio() * 100.0),
+                format!("{:.1} %", simulated.ratio() * 100.0))));
+            let failed_count = simulation.failed_count.to_string();
+            ui.add(components::Field::new("Failed allocations", &failed_count));
+        }
+        ui.add_space(16.0);
+        ui.add(components::SectionTitle(String::from("Breakpoints")));
+        let mut removed_idx = None;
+        let mut toggled = None;
+        for (idx, breakpoint) in self.memory.breakpoints().iter().enumerate() {
+            ui.horizontal(|ui| {
+                let mut is_enabled = breakpoint.is_enabled;
+                if ui.checkbox(&mut is_enabled, breakpoint.condition.to_string())
+                    .changed() {
+                    toggled = Some((idx, is_enabled));
+                }
+                if ui.small_button("x").clicked() {
+                    removed_idx = Some(idx);
+                }
+            });
+        }
+        if let Some((idx, is_enabled)) = toggled {
+            self.memory.set_breakpoint_enabled(idx, is_enabled);
+        }
+        if let Some(idx) = removed_idx {
+            self.memory.remove_breakpoint(idx);
+        }
+        if let Some(condition) = self.breakpoint_form.show(ui) {
+            self.memory.add_breakpoint(condition);
+        }
+        ui.add_space(16.0);
+        ui.add(components::SectionTitle(String::from("Policies")));
+        for kind in alloc::AnomalyKind::ALL {
+            let mut policy = self.memory.policy(kind);
+            ui.horizontal(|ui| {
+                ui.label(kind.to_string());
+                egui::ComboBox::from_id_source(kind)
+                    .selected_text(policy.to_string())
+                    .show_ui(ui, |ui| {
+                        for option in alloc::AnomalyPolicy::ALL {
+                            ui.selectable_value(&mut policy, option, option.to_string());
+                        }
+                    });
+            });
+            if policy != self.memory.policy(kind) {
+                self.memory.set_policy(kind, policy);
+            }
+        }
+        ui.add_space(16.0);
+        ui.add(components::SectionTitle(String::from("Search")));
+        let (filter, jump) = self.filter_form.show(ui);
+        if let Some(filter) = filter {
+            self.memory.set_filter(Some(filter).filter(|filter| !filter.is_empty()));
+        }
+        if jump {
+            self.memory.jump_to_first_match();
+        }
+        self.selection_section(ui);
+        self.chunk_section(ui);
+        if let Some(anomaly) = self.selected_anomaly
+            .and_then(|idx| self.memory.anomalies().get(idx)) {
+            ui.add_space(16.0);
+            let ptr = format!("{:#01x}", anomaly.ptr);
+            let len = format!("{} bytes", anomaly.len);
+            ui.add(components::SectionTitle(String::from("Anomaly")));
+            ui.add(components::Field::new("Kind", &anomaly.kind.to_string()));
+            ui.add(components::Field::new("Ptr", &ptr));
+            if anomaly.len > 0 {
+                ui.add(components::Field::new("Length", &len));
+            }
+            ui.add(components::Field::new("Details", &anomaly.identifier));
+        }
+        if !self.memory.anomalies().is_empty() {
+            ui.add_space(16.0);
+            ui.add(components::SectionTitle(String::from("Anomalies")));
+            let mut clicked_idx = None;
+            egui::ScrollArea::vertical()
+                .max_height(ANOMALY_LIST_HEIGHT)
+                .show(ui, |ui| {
+                    for (idx, anomaly) in self.memory.anomalies().iter().enumerate().rev() {
+                        let label = format!("{} at {:#01x}", anomaly.kind, anomaly.ptr);
+                        if ui.link(label).clicked() {
+                            clicked_idx = Some(idx);
+                        }
+                    }
+                });
+            if let Some(idx) = clicked_idx {
+                let anomaly = &self.memory.anomalies()[idx];
+                let ptr = anomaly.owner.unwrap_or(anomaly.ptr);
+                self.memory.select(Some(ptr));
+                self.selected_anomaly = clicked_idx;
+            }
+        }
+    }
+
+    /// The chunk at the selected address, or its state once freed, and what happened there before.
+    fn chunk_section(&mut self, ui: &mut egui::Ui) {
+        let Some(ptr) = self.memory.selected_ptr() else {
+            return;
+        };
+        let chunk = self.memory.selected_chunk();
+        let history = address_history(self.memory.chunks(), ptr);
+        if chunk.is_none() && history.is_empty() {
+            return;
+        }
+        ui.add_space(16.0);
+        ui.add(components::SectionTitle(String::from("Chunk")));
+        ui.add(components::Field::new("Ptr", &format!("{:#01x}", ptr)));
+        match &chunk {
+            Some(chunk) => {
+                let size = format!("{} bytes", chunk.size);
+                ui.add(components::Field::new("Size", &size));
+                ui.add(components::Field::new("Identifiers", &chunk.identifier));
+                for identifier in chunk.free_identifiers.iter() {
+                    ui.add(components::Field::new("Freed by", identifier));
+                }
+                ui.add(components::Field::new("State", &chunk.state.to_string()));
+                if let Some(thread) = chunk.thread {
+                    ui.add(components::Field::new("Thread", &format!("{:x}", thread)));
+                }
+                if let Some(allocated_at) = chunk.allocated_at {
+                    ui.add(components::Field::new("Allocated at", &allocated_at.to_string()));
+                }
+                if let Some(freed_at) = chunk.freed_at {
+                    ui.add(components::Field::new("Freed at", &freed_at.to_string()));
+                }
+                for anomaly in self.memory.anomalies().iter()
+                    .filter(|anomaly| anomaly.owner == Some(chunk.ptr)) {
+                    let value = format!("{:#01x} {}", anomaly.ptr, anomaly.identifier);
+                    ui.add(components::Field::new(&anomaly.kind.to_string(), &value));
+                }
+            }
+            None => {
+                ui.add(components::Field::new("State", "freed"));
+            }
+        }
+        if history.len() > 1 || chunk.is_none() {
+            ui.label(egui::RichText::new("History").color(egui::Color32::GRAY));
+            egui::ScrollArea::vertical()
+                .id_source("address_history")
+                .max_height(ANOMALY_LIST_HEIGHT)
+                .show(ui, |ui| {
+                    for (event_idx, text) in history {
+                        ui.add(components::Field::new(&format!("#{}", event_idx), &text));
+                    }
+                });
+        }
+    }
+
+    /// Selected chunks taken together, what can be done with them, and the pinned chunks.
+    fn selection_section(&mut self, ui: &mut egui::Ui) {
+        let selected = self.memory.selected_chunks();
+        let pinned = self.memory.pinned();
+        if selected.is_empty() && pinned.is_empty() && self.memory.hidden_count() == 0 {
+            return;
+        }
+        ui.add_space(16.0);
+        ui.add(components::SectionTitle(String::from("Selection")));
+        let count = selected.len().to_string();
+        let size = format!("{} bytes", selected.iter().map(|chunk| chunk.size).sum::<u64>());
+        ui.add(components::Field::new("Chunks", &count));
+        ui.add(components::Field::new("Total size", &size));
+        let mut identifiers = BTreeMap::<&str, usize>::new();
+        for chunk in selected.iter() {
+            *identifiers.entry(&chunk.identifier).or_default() += 1;
+        }
+        for (identifier, count) in identifiers {
+            ui.add(components::Field::new(identifier_label(identifier), &count.to_string()));
+        }
+        let mut pinned_clicked = None;
+        let mut unpinned = None;
+        for &(key, chunk) in pinned.iter() {
+            let (ptr, _) = key;
+            ui.horizontal(|ui| {
+                let label = match chunk {
+                    Some(chunk) => format!("Pinned {:#01x} {}", ptr, identifier_label(
+                        &chunk.identifier)),
+                    None => format!("Pinned {:#01x} (freed)", ptr),
+                };
+                if ui.link(label).clicked() {
+                    pinned_clicked = Some(ptr);
+                }
+                if ui.small_button("x").clicked() {
+                    unpinned = Some(key);
+                }
+            });
+        }
+        let has_selection = !selected.is_empty();
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(has_selection, |ui| {
+                if ui.button("Hide").clicked() {
+                    self.memory.hide_selection();
+                }
+                if ui.button("Pin").clicked() {
+                    self.memory.pin_selection();
+                }
+                if ui.button("Same identifier").clicked() {
+                    self.memory.select_same_identifiers();
+                }
+                if ui.button("Copy").clicked() {
+                    let report = self.memory.selection_report();
+                    ui.output_mut(|output| output.copied_text = report);
+                    self.selection_status = String::from("Copied");
+                }
+            });
+        });
+        if self.memory.hidden_count() > 0 {
+            let label = format!("Show {} hidden", self.memory.hidden_count());
+            if ui.button(label).clicked() {
+                self.memory.show_hidden();
+            }
+        }
+        ui.label(&self.selection_status);
+        if let Some(ptr) = pinned_clicked {
+            self.memory.go_to(ptr);
+        }
+        if let Some(key) = unpinned {
+            self.memory.unpin(key);
         }
     }
 }
 
+/// What happened at `ptr`, by event index: every chunk allocated there, who freed it and the
+/// chunks it collided with.
+fn address_history(chunks: &alloc::Chunks, ptr: u64) -> Vec<(usize, String)> {
+    let mut history = Vec::new();
+    for past in chunks.past_chunks(ptr).chain(chunks.get(ptr)) {
+        let thread = |thread: Option<u64>| thread.map_or(String::new(),
+            |thread| format!(" on thread {:x}", thread));
+        if past.is_bad_free() {
+            if let Some(freed_event) = past.freed_event {
+                history.push((freed_event, format!("{} by {}", past.state,
+                    past.free_identifiers.join(", "))));
+            }
+            continue;
+        }
+        history.push((past.allocated_event, format!("{} bytes by {}{}", past.size,
+            identifier_label(&past.identifier), thread(past.thread))));
+        for (event_idx, other) in past.collisions.iter() {
+            history.push((*event_idx, format!("collided with {:#01x}", other)));
+        }
+        if let Some(freed_event) = past.freed_event {
+            history.push((freed_event, format!("freed by {}{}", past.free_identifiers.join(", "),
+                thread(past.free_thread))));
+        }
+    }
+    // Sorting is stable, so an allocation stays before a collision of the same event.
+    history.sort_by_key(|(event_idx, _)| *event_idx);
+    history
+}
+
 impl eframe::App for App {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.memory.update();
         egui::SidePanel::right("sidepanel")
             .min_width(SIDE_PANEL_WIDTH)
             .resizable(false)
             .show(ctx, |ui| {
-                ui.add_space(16.0);
-                ui.add(components::SectionTitle(String::from("Controls")));
-                let play_pause_button_label = match self.memory.do_advance() {
-                    true => "Pause",
-                    false => "Play",
-                };
-                if ui.button(play_pause_button_label).clicked() {
-                    self.memory.set_do_advance(!self.memory.do_advance());
-                }
-                if let Some(chunk) = self.memory.selected_chunk() {
-                    ui.add_space(16.0);
-                    let ptr = format!("{:#01x}", chunk.ptr);
-                    let size = format!("{} bytes", chunk.size);
-                    ui.add(components::SectionTitle(String::from("Chunk")));
-                    ui.add(components::Field::new("Ptr", &ptr));
-                    ui.add(components::Field::new("Size", &size));
-                    ui.add(components::Field::new("Identifiers", &chunk.identifier));
-                    ui.add(components::Field::new("State", &chunk.state.to_string()));
-                }
+                egui::ScrollArea::vertical().show(ui, |ui| self.side_panel(ui));
             });
+        egui::TopBottomPanel::bottom("usage")
+            .default_height(USAGE_PANEL_HEIGHT)
+            .resizable(true)
+            .show(ctx, |ui| {
+                let chunks = self.memory.chunks();
+                let history = chunks.history();
+                ui.horizontal(|ui| {
+                    ui.add(components::SectionTitle(String::from("Usage")));
+                    let live_bytes = format!("Live {} bytes", chunks.live_bytes());
+                    let live_count = format!("{} chunks", chunks.live_count());
+                    let largest_gap = format!("Largest gap {} bytes",
+                        chunks.fragmentation().largest_size());
+                    ui.label(egui::RichText::new(live_bytes)
+                        .color(components::usage_graph::COLOR_LIVE_BYTES));
+                    ui.label(egui::RichText::new(live_count)
+                        .color(components::usage_graph::COLOR_LIVE_COUNT));
+                    ui.label(egui::RichText::new(largest_gap)
+                        .color(components::usage_graph::COLOR_LARGEST_GAP));
+                    ui.label(format!("Peak {} bytes in {} chunks", history.peak_bytes(),
+                        history.peak_count()));
+                    ui.separator();
+                    ui.selectable_value(&mut self.time_axis,
+                        components::usage_graph::TimeAxis::Events, "Events");
+                    ui.selectable_value(&mut self.time_axis,
+                        components::usage_graph::TimeAxis::Seconds, "Time");
+                });
+                ui.add(components::UsageGraph::new(history, chunks.anomalies(), self.time_axis));
+            });
+        let view = self.views.selected().unwrap_or(View::Map);
+        if view == View::Map {
+            egui::SidePanel::left("minimap")
+                .exact_width(MINIMAP_WIDTH)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.add(components::Minimap::new(&mut self.memory));
+                });
+        }
         egui::CentralPanel::default().show(ctx, |ui| {
-            ui.add(&mut self.memory);
+            ui.add(&mut self.views);
+            match view {
+                View::Map => {
+                    ui.add(&mut self.memory);
+                }
+                View::Bar => {
+                    self.heap_bar.show(ui, &mut self.memory);
+                }
+                View::Treemap => {
+                    let clicked = components::Treemap::new(self.memory.chunks()).show(ui);
+                    // Clicking a tile shows the chunks of that identifier on the map.
+                    if let Some(identifier) = clicked {
+                        let filter = self.filter_form.set_identifier(&identifier);
+                        self.memory.set_filter(Some(filter));
+                        self.memory.jump_to_first_match();
+                        self.views.select(0);
+                    }
+                }
+            }
         });
     }
 }
@@ -0,0 +1,309 @@
+use std::{collections::{BTreeMap, BTreeSet, HashMap, VecDeque}, fmt, ops};
+
+use crate::fragmentation::Fragmentation;
+
+/// Every chunk handed out by a simulated allocator is aligned on this many bytes.
+const ALIGN: u64 = 8;
+/// Number of bits of the second level of the TLSF size classes.
+const TLSF_SL_BITS: u32 = 4;
+/// Operations kept in the trace. Older ones are folded into the chunks the replay starts with.
+const MAX_TRACE_OPS: usize = 1 << 20;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Strategy {
+    FirstFit,
+    BestFit,
+    Tlsf,
+}
+
+impl Strategy {
+    pub const ALL: [Strategy; 3] = [Strategy::FirstFit, Strategy::BestFit, Strategy::Tlsf];
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Strategy::FirstFit => write!(f, "first fit"),
+            Strategy::BestFit => write!(f, "best fit"),
+            Strategy::Tlsf => write!(f, "TLSF"),
+        }
+    }
+}
+
+/// An allocation or a free as seen by the allocator, kept to replay the trace.
+#[derive(Clone, Copy)]
+pub enum TraceOp {
+    Alloc { ptr: u64, size: u64 },
+    Free { ptr: u64 },
+}
+
+/// The allocations and frees to replay, bounded by `MAX_TRACE_OPS`.
+#[derive(Default)]
+pub struct Trace {
+    ops: VecDeque<TraceOp>,
+    /// Chunks live before the oldest operation kept, by address, with their size.
+    base: BTreeMap<u64, u64>,
+}
+
+impl Trace {
+    pub fn push(&mut self, op: TraceOp) {
+        if self.ops.len() == MAX_TRACE_OPS {
+            match self.ops.pop_front() {
+                Some(TraceOp::Alloc { ptr, size }) => {
+                    self.base.insert(ptr, size);
+                }
+                Some(TraceOp::Free { ptr }) => {
+                    self.base.remove(&ptr);
+                }
+                None => {}
+            }
+        }
+        self.ops.push_back(op);
+    }
+}
+
+fn align_up(value: u64) -> u64 {
+    // Sizes come from the tracked process, one too large to align never fits anyway.
+    value.div_ceil(ALIGN).saturating_mul(ALIGN)
+}
+
+/// TLSF size class of `size`, as a first level and a second level index.
+fn tlsf_class(size: u64) -> (u32, u64) {
+    let size = size.max(1);
+    let fl = u64::BITS - 1 - size.leading_zeros();
+    if fl < TLSF_SL_BITS {
+        return (0, size);
+    }
+    (fl, (size >> (fl - TLSF_SL_BITS)) - (1 << TLSF_SL_BITS))
+}
+
+/// Rounds `size` up to the next TLSF size class, so every block of that class is large enough.
+fn tlsf_round_up(size: u64) -> u64 {
+    let fl = u64::BITS - 1 - size.max(1).leading_zeros();
+    if fl < TLSF_SL_BITS {
+        return size;
+    }
+    size.saturating_add((1 << (fl - TLSF_SL_BITS)) - 1)
+}
+
+/// Returns where the allocator using `strategy` would put `size` bytes given the free `gaps`,
+/// sorted by address.
+pub fn place(strategy: Strategy, gaps: &[ops::Range<u64>], size: u64) -> Option<u64> {
+    let size = align_up(size.max(1));
+    let usable = gaps
+        .iter()
+        .map(|gap| align_up(gap.start)..gap.end)
+        .filter(|gap| gap.start < gap.end && gap.end - gap.start >= size);
+    match strategy {
+        Strategy::FirstFit => usable.map(|gap| gap.start).next(),
+        Strategy::BestFit => usable
+            .min_by_key(|gap| gap.end - gap.start)
+            .map(|gap| gap.start),
+        // TLSF takes the first block of the smallest class that is guaranteed to fit, which makes
+        // it a good fit rather than a best fit.
+        Strategy::Tlsf => {
+            let class = tlsf_class(tlsf_round_up(size));
+            usable
+                .filter(|gap| tlsf_class(gap.end - gap.start) >= class)
+                .min_by_key(|gap| tlsf_class(gap.end - gap.start))
+                .map(|gap| gap.start)
+        }
+    }
+}
+
+/// Free gaps of a simulated heap, by address and by size. Every gap starts aligned, since the heap
+/// start and every simulated chunk are.
+#[derive(Default)]
+struct Gaps {
+    /// End of every gap by its start.
+    by_start: BTreeMap<u64, u64>,
+    /// Size and start of every gap.
+    by_size: BTreeSet<(u64, u64)>,
+}
+
+impl Gaps {
+    fn insert(&mut self, gap: ops::Range<u64>) {
+        if gap.start < gap.end {
+            self.by_start.insert(gap.start, gap.end);
+            self.by_size.insert((gap.end - gap.start, gap.start));
+        }
+    }
+
+    fn remove(&mut self, start: u64) -> Option<ops::Range<u64>> {
+        let end = self.by_start.remove(&start)?;
+        self.by_size.remove(&(end - start, start));
+        Some(start..end)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = ops::Range<u64>> + '_ {
+        self.by_start.iter().map(|(&start, &end)| start..end)
+    }
+
+    /// Same as `place`, without going through every gap for the best fit and TLSF.
+    fn place(&self, strategy: Strategy, size: u64) -> Option<u64> {
+        let mut by_size = self.by_size.range((size, 0)..).copied();
+        match strategy {
+            Strategy::FirstFit => self.iter().find(|gap| gap.end - gap.start >= size)
+                .map(|gap| gap.start),
+            Strategy::BestFit => by_size.next().map(|(_, start)| start),
+            Strategy::Tlsf => {
+                let class = tlsf_class(tlsf_round_up(size));
+                let mut candidates = by_size
+                    .map(|(gap_size, start)| (tlsf_class(gap_size), start))
+                    .skip_while(|&(gap_class, _)| gap_class < class)
+                    .peekable();
+                let &(found, _) = candidates.peek()?;
+                candidates
+                    .take_while(|&(gap_class, _)| gap_class == found)
+                    .map(|(_, start)| start)
+                    .min()
+            }
+        }
+    }
+
+    /// Takes `ptr..ptr + size` out of the gap it lies in.
+    fn take(&mut self, ptr: u64, size: u64) {
+        let Some((&start, _)) = self.by_start.range(..=ptr).next_back() else {
+            return;
+        };
+        if let Some(gap) = self.remove(start) {
+            self.insert(gap.start..ptr);
+            self.insert(ptr + size..gap.end);
+        }
+    }
+
+    /// Gives `ptr..ptr + size` back, merged with the gaps right before and after it.
+    fn give_back(&mut self, ptr: u64, size: u64) {
+        let mut gap = ptr..ptr + size;
+        let previous = self.by_start.range(..ptr).next_back().map(|(&start, &end)| start..end);
+        if let Some(previous) = previous.filter(|previous| previous.end == ptr) {
+            self.remove(previous.start);
+            gap.start = previous.start;
+        }
+        if let Some(next) = self.remove(gap.end) {
+            gap.end = next.end;
+        }
+        self.insert(gap);
+    }
+}
+
+/// The layout a simulated allocator ends up with after replaying a trace.
+pub struct Simulation {
+    pub strategy: Strategy,
+    heap: ops::Range<u64>,
+    /// Simulated chunks by address, with their size.
+    chunks: BTreeMap<u64, u64>,
+    gaps: Gaps,
+    /// Simulated address of every live chunk by its real address.
+    by_real_ptr: HashMap<u64, u64>,
+    /// Allocations that did not fit anywhere.
+    pub failed_count: usize,
+    peak_end: u64,
+}
+
+impl Simulation {
+    /// The chunks that were live before the oldest operation of the trace are allocated first, by
+    /// address.
+    pub fn replay(strategy: Strategy, heap: ops::Range<u64>, trace: &Trace) -> Self {
+        let start = align_up(heap.start).min(heap.end);
+        let mut gaps = Gaps::default();
+        gaps.insert(start..heap.end);
+        let mut simulation = Self {
+            strategy,
+            peak_end: heap.start,
+            heap,
+            chunks: BTreeMap::new(),
+            gaps,
+            by_real_ptr: HashMap::new(),
+            failed_count: 0,
+        };
+        for (&ptr, &size) in trace.base.iter() {
+            simulation.alloc(ptr, size);
+        }
+        for op in trace.ops.iter() {
+            match *op {
+                TraceOp::Alloc { ptr, size } => simulation.alloc(ptr, size),
+                TraceOp::Free { ptr } => simulation.free(ptr),
+            }
+        }
+        simulation
+    }
+
+    fn alloc(&mut self, real_ptr: u64, size: u64) {
+        let size = align_up(size.max(1));
+        match self.gaps.place(self.strategy, size) {
+            Some(ptr) => {
+                self.gaps.take(ptr, size);
+                self.chunks.insert(ptr, size);
+                self.by_real_ptr.insert(real_ptr, ptr);
+                self.peak_end = self.peak_end.max(ptr + size);
+            }
+            None => self.failed_count += 1,
+        }
+    }
+
+    fn free(&mut self, real_ptr: u64) {
+        let Some(ptr) = self.by_real_ptr.remove(&real_ptr) else {
+            return;
+        };
+        if let Some(size) = self.chunks.remove(&ptr) {
+            self.gaps.give_back(ptr, size);
+        }
+    }
+
+    /// Ranges of the simulated chunks sharing bytes with `range`, sorted by address.
+    pub fn ranges_in(&self, range: ops::Range<u64>) -> impl Iterator<Item = ops::Range<u64>> + '_ {
+        // Simulated chunks never overlap, so only the last one starting before `range` can reach
+        // into it.
+        let first = self.chunks
+            .range(..range.start)
+            .next_back()
+            .map_or(range.start, |(&ptr, _)| ptr);
+        self.chunks
+            .range(first..range.end)
+            .map(|(&ptr, &size)| ptr..ptr + size)
+            .filter(move |chunk| chunk.end > range.start)
+    }
+
+    pub fn fragmentation(&self) -> Fragmentation {
+        Fragmentation::from_gaps(self.gaps.iter())
+    }
+
+    /// Highest address ever used, relative to the start of the heap.
+    pub fn footprint(&self) -> u64 {
+        self.peak_end - self.heap.start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The gap index must agree with computing the gaps and placing from scratch.
+    #[test]
+    fn gap_index_matches_placing_from_scratch() {
+        for strategy in Strategy::ALL {
+            let heap = 0x10..0x4003;
+            let mut simulation = Simulation::replay(strategy, heap.clone(), &Trace::default());
+            let mut seed = 0x2545f491u64;
+            let mut live = Vec::new();
+            for _ in 0..2000 {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                let random = seed >> 33;
+                if random.is_multiple_of(3) && !live.is_empty() {
+                    simulation.free(live.swap_remove(random as usize % live.len()));
+                } else {
+                    let ptr = random << 16;
+                    live.push(ptr);
+                    simulation.alloc(ptr, random % 300);
+                }
+                let ranges = simulation.ranges_in(heap.clone()).collect::<Vec<ops::Range<u64>>>();
+                let gaps = Fragmentation::new(&heap, &ranges).gaps;
+                assert!(simulation.gaps.iter().eq(gaps.iter().cloned()));
+                for size in [8, 24, 200, 1000] {
+                    assert_eq!(simulation.gaps.place(strategy, size), place(strategy, &gaps, size));
+                }
+            }
+        }
+    }
+}
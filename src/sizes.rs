@@ -0,0 +1,135 @@
+use std::{collections::{BTreeMap, HashMap}, fmt::Write};
+
+/// Class of the sizes above the largest power of two, which can only come from a broken process.
+const TOP_CLASS: u64 = 1 << 63;
+
+/// Pools hand out blocks of a power of two bytes, so sizes are grouped the same way.
+pub fn class_of(size: u64) -> u64 {
+    size.max(1).checked_next_power_of_two().unwrap_or(TOP_CLASS)
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct SizeClass {
+    /// Allocations made in this class.
+    pub count: usize,
+    pub live: usize,
+    /// Highest number of chunks of this class alive at the same time.
+    pub peak: usize,
+}
+
+/// Distribution of the allocation sizes, overall and per identifier.
+#[derive(Default)]
+pub struct Sizes {
+    classes: BTreeMap<u64, SizeClass>,
+    by_identifier: HashMap<String, BTreeMap<u64, usize>>,
+}
+
+impl Sizes {
+    pub fn observe_alloc(&mut self, size: u64, identifier: &str) {
+        let class = class_of(size);
+        self.classes.entry(class).or_default().count += 1;
+        *self.by_identifier
+            .entry(identifier.to_string())
+            .or_default()
+            .entry(class)
+            .or_default() += 1;
+    }
+
+    pub fn add_live(&mut self, size: u64) {
+        let class = self.classes.entry(class_of(size)).or_default();
+        class.live += 1;
+        class.peak = class.peak.max(class.live);
+    }
+
+    pub fn remove_live(&mut self, size: u64) {
+        if let Some(class) = self.classes.get_mut(&class_of(size)) {
+            class.live = class.live.saturating_sub(1);
+        }
+    }
+
+    pub fn classes(&self) -> &BTreeMap<u64, SizeClass> {
+        &self.classes
+    }
+
+    pub fn by_identifier(&self) -> &HashMap<String, BTreeMap<u64, usize>> {
+        &self.by_identifier
+    }
+
+    /// Pools that would have served every allocation seen so far, as a block count and a block
+    /// size, smallest blocks first.
+    pub fn proposal(&self) -> Vec<(usize, u64)> {
+        self.classes
+            .iter()
+            .filter(|(_, class)| class.peak > 0)
+            .map(|(&size, class)| (class.peak, size))
+            .collect()
+    }
+
+    pub fn proposal_text(&self) -> String {
+        self.proposal()
+            .iter()
+            .map(|(count, size)| format!("{} x {}B", count, size))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    pub fn report(&self) -> String {
+        let mut report = String::from("heapvue pool sizing report\n\n");
+        let _ = writeln!(report, "{:>10} {:>10} {:>10}", "class", "allocs", "peak");
+        for (size, class) in self.classes.iter() {
+            let _ = writeln!(report, "{:>9}B {:>10} {:>10}", size, class.count, class.peak);
+        }
+        let _ = writeln!(report, "\nper identifier");
+        let mut identifiers = self.by_identifier.iter().collect::<Vec<_>>();
+        identifiers.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        for (identifier, classes) in identifiers {
+            let classes = classes
+                .iter()
+                .map(|(size, count)| format!("{} x {}B", count, size))
+                .collect::<Vec<String>>()
+                .join(", ");
+            let _ = writeln!(report, "  {}: {}", identifier, classes);
+        }
+        let total = self.proposal()
+            .iter()
+            .map(|&(count, size)| (count as u64).saturating_mul(size))
+            .fold(0, u64::saturating_add);
+        let _ = writeln!(report, "\nproposed pools: {}", self.proposal_text());
+        let _ = writeln!(report, "total: {} bytes", total);
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_are_rounded_up_to_a_power_of_two() {
+        assert_eq!(class_of(0), 1);
+        assert_eq!(class_of(1), 1);
+        assert_eq!(class_of(8), 8);
+        assert_eq!(class_of(9), 16);
+        assert_eq!(class_of(TOP_CLASS), TOP_CLASS);
+        assert_eq!(class_of(TOP_CLASS + 1), TOP_CLASS);
+        assert_eq!(class_of(u64::MAX), TOP_CLASS);
+    }
+
+    #[test]
+    fn pools_are_sized_by_the_peak_of_each_class() {
+        let mut sizes = Sizes::default();
+        for size in [8, 5, 9, 16, 16] {
+            sizes.observe_alloc(size, "site");
+            sizes.add_live(size);
+        }
+        sizes.remove_live(16);
+        sizes.remove_live(9);
+        sizes.observe_alloc(12, "other_site");
+        sizes.add_live(12);
+        assert_eq!(sizes.proposal(), [(2, 8), (3, 16)]);
+        assert_eq!(sizes.proposal_text(), "2 x 8B, 3 x 16B");
+        assert!(sizes.report().ends_with("total: 64 bytes\n"));
+        assert_eq!(sizes.classes()[&16].count, 4);
+        assert_eq!(sizes.by_identifier()["other_site"][&16], 1);
+    }
+}
@@ -33,5 +33,5 @@ while True:
     if random.randrange(24) == 1:
         ptr_int = random.randrange(200000)
         ptr = hex(ptr_int)[2:]
-        print(f"c:{ptr},aa")
+        print(f"c:{ptr},10,canary,guard bytes overwritten")
         sys.stdout.flush()